@@ -0,0 +1,498 @@
+// parses the command line into a Command; no arguments means the numbered menu
+use crate::output::Format;
+use crate::statusline::LineStyle;
+use crate::tracker::MAX_MINUTES;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: pomo [--data-dir <path>] [--portable] [COMMAND]
+
+Without a command, pomo starts the interactive menu.
+
+Commands:
+  start <project> [--preset <name>] [--focus <min>] [--rest <min>] [--long-rest <min>]
+        [--long-break-every <n>] [--cycles <n>] [--count-skipped-rests]
+        [--save-defaults | --clear-defaults] [--flow]
+                                  Run focus/rest cycles on a project; --save-defaults
+                                  keeps the lengths as the project's own; --flow
+                                  counts focus up and suggests breaks (Flowtime)
+  new <name>                      Create a project
+  status [--format <fmt>]         Show the focus dashboard
+  status --line [--template <tpl>] [--waybar | --i3bar]
+                                  Print one line about the running timer, or
+                                  nothing when none runs
+  projects [--archived] [--format <fmt>]
+                                  List active (or archived) projects
+  sessions [<project>] [--limit <n>] [--format <fmt>]
+                                  List recorded sessions, newest first
+  journal <project>               Open a project's journal in $EDITOR
+  rename <project> <new name>     Rename a project, keeping its history
+  delete <project> [--yes]        Archive a project
+  restore <project>               Restore an archived project
+  purge <project> [--yes]         Permanently delete an archived project
+  log <project> --start <time> (--minutes <min> | --end <time>)
+                                  Record focus time spent away from the timer
+  undo [--yes]                    Undo the last recorded focus block
+  presets                         List the timer presets
+  config [<key> [<value> | --unset]]
+                                  Show or change a setting
+  hooks                           List the hook events and installed hooks
+  daemon                          Run the timer in the background, controlled
+                                  through a socket (Unix only)
+  ctl <action>                    Control the daemon's timer, where <action> is
+                                  start <project> [--preset <name>] [--flow],
+                                  pause, resume, skip, stop, status or
+                                  switch <project>
+  help                            Show this message
+
+Times are local, as 'YYYY-MM-DD HH:MM' or 'HH:MM' for today.
+<fmt> is text (default), json, csv or tsv; --json is short for --format json.
+Project names containing spaces must be quoted.";
+
+pub struct Invocation {
+    pub data_dir: Option<PathBuf>,
+    pub portable: bool,
+    pub command: Command,
+}
+
+pub enum Command {
+    Menu,
+    Help,
+    Start {
+        project: String,
+        preset: Option<String>,
+        focus_minutes: Option<f32>,
+        rest_minutes: Option<f32>,
+        long_rest_minutes: Option<f32>,
+        long_break_interval: Option<u64>,
+        cycles: Option<u64>,
+        count_skipped_rests: bool,
+        save_defaults: bool,
+        clear_defaults: bool,
+        flow: bool,
+    },
+    New {
+        name: String,
+    },
+    Status {
+        format: Format,
+        // one line about the running timer instead of the dashboard
+        line: Option<LineStyle>,
+        template: Option<String>,
+    },
+    Projects {
+        archived: bool,
+        format: Format,
+    },
+    Sessions {
+        project: Option<String>,
+        limit: usize,
+        format: Format,
+    },
+    Journal {
+        project: String,
+    },
+    Rename {
+        project: String,
+        new_name: String,
+    },
+    Delete {
+        project: String,
+        yes: bool,
+    },
+    Restore {
+        project: String,
+    },
+    Purge {
+        project: String,
+        yes: bool,
+    },
+    Log {
+        project: String,
+        start: String,
+        minutes: Option<f32>,
+        end: Option<String>,
+    },
+    Undo {
+        yes: bool,
+    },
+    Presets,
+    Hooks,
+    Config {
+        key: Option<String>,
+        value: Option<String>,
+        unset: bool,
+    },
+    Daemon,
+    Ctl(Control),
+}
+
+// a request to the daemon, as sent by `pomo ctl`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Control {
+    Start {
+        project: String,
+        preset: Option<String>,
+        flow: bool,
+    },
+    Pause,
+    Resume,
+    Skip,
+    Stop,
+    Status,
+    Switch {
+        project: String,
+    },
+}
+
+pub fn parse(args: impl Iterator<Item = String>) -> Result<Invocation, String> {
+    let mut data_dir = None;
+    let mut portable = false;
+    let mut rest = Vec::new();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        if arg == "--portable" {
+            portable = true;
+        } else if arg == "--data-dir" {
+            let dir = args.next().ok_or("--data-dir needs a path")?;
+            data_dir = Some(PathBuf::from(dir));
+        } else if let Some(dir) = arg.strip_prefix("--data-dir=") {
+            data_dir = Some(PathBuf::from(dir));
+        } else {
+            rest.push(arg);
+        }
+    }
+    Ok(Invocation {
+        data_dir,
+        portable,
+        command: parse_command(rest)?,
+    })
+}
+
+fn parse_command(args: Vec<String>) -> Result<Command, String> {
+    let Some((name, rest)) = args.split_first() else {
+        return Ok(Command::Menu);
+    };
+    let command = match name.as_str() {
+        "help" | "--help" | "-h" => Command::Help,
+        "start" => {
+            let opts = Options::parse(
+                rest,
+                &[
+                    "preset",
+                    "focus",
+                    "rest",
+                    "long-rest",
+                    "long-break-every",
+                    "cycles",
+                ],
+                &[
+                    "count-skipped-rests",
+                    "save-defaults",
+                    "clear-defaults",
+                    "flow",
+                ],
+            )?;
+            Command::Start {
+                project: opts.single_positional("start", "<project>")?,
+                preset: opts.value("preset").map(str::to_string),
+                focus_minutes: opts.minutes("focus")?,
+                rest_minutes: opts.minutes("rest")?,
+                long_rest_minutes: opts.minutes("long-rest")?,
+                long_break_interval: opts.count("long-break-every")?,
+                cycles: opts.count("cycles")?,
+                count_skipped_rests: opts.flag("count-skipped-rests"),
+                save_defaults: opts.flag("save-defaults"),
+                clear_defaults: opts.flag("clear-defaults"),
+                flow: opts.flag("flow"),
+            }
+        }
+        "new" => {
+            let opts = Options::parse(rest, &[], &[])?;
+            Command::New {
+                name: opts.single_positional("new", "<name>")?,
+            }
+        }
+        "status" => {
+            let opts = Options::parse(
+                rest,
+                &["format", "template"],
+                &["json", "line", "waybar", "i3bar"],
+            )?;
+            opts.no_positionals("status")?;
+            let template = opts.value("template").map(str::to_string);
+            let line = if opts.flag("waybar") {
+                Some(LineStyle::Waybar)
+            } else if opts.flag("i3bar") {
+                Some(LineStyle::I3bar)
+            } else if opts.flag("line") || template.is_some() {
+                Some(LineStyle::Plain)
+            } else {
+                None
+            };
+            let format = opts.format()?;
+            if line.is_some() && format != Format::Text {
+                return Err(
+                    "pomo status --line takes no --format; try --waybar or --i3bar".to_string(),
+                );
+            }
+            Command::Status {
+                format,
+                line,
+                template,
+            }
+        }
+        "projects" => {
+            let opts = Options::parse(rest, &["format"], &["archived", "json"])?;
+            opts.no_positionals("projects")?;
+            Command::Projects {
+                archived: opts.flag("archived"),
+                format: opts.format()?,
+            }
+        }
+        "sessions" => {
+            let opts = Options::parse(rest, &["format", "limit"], &["json"])?;
+            let project = match opts.positionals.as_slice() {
+                [] => None,
+                [project] => Some(project.clone()),
+                _ => return Err("Usage: pomo sessions [<project>]".to_string()),
+            };
+            let limit = match opts.value("limit") {
+                None => 20,
+                Some(value) => value
+                    .parse::<usize>()
+                    .map_err(|_| format!("--limit needs a number, got '{}'", value))?,
+            };
+            Command::Sessions {
+                project,
+                limit,
+                format: opts.format()?,
+            }
+        }
+        "journal" => {
+            let opts = Options::parse(rest, &[], &[])?;
+            Command::Journal {
+                project: opts.single_positional("journal", "<project>")?,
+            }
+        }
+        "rename" => {
+            let opts = Options::parse(rest, &[], &[])?;
+            match opts.positionals.as_slice() {
+                [project, new_name] => Command::Rename {
+                    project: project.clone(),
+                    new_name: new_name.clone(),
+                },
+                _ => return Err("Usage: pomo rename <project> <new name>".to_string()),
+            }
+        }
+        "delete" => {
+            let opts = Options::parse(rest, &[], &["yes"])?;
+            Command::Delete {
+                project: opts.single_positional("delete", "<project>")?,
+                yes: opts.flag("yes"),
+            }
+        }
+        "restore" => {
+            let opts = Options::parse(rest, &[], &[])?;
+            Command::Restore {
+                project: opts.single_positional("restore", "<project>")?,
+            }
+        }
+        "purge" => {
+            let opts = Options::parse(rest, &[], &["yes"])?;
+            Command::Purge {
+                project: opts.single_positional("purge", "<project>")?,
+                yes: opts.flag("yes"),
+            }
+        }
+        "log" => {
+            let opts = Options::parse(rest, &["start", "minutes", "end"], &[])?;
+            let project = opts.single_positional("log", "<project>")?;
+            let start = opts
+                .value("start")
+                .ok_or("pomo log needs --start <time>")?
+                .to_string();
+            let minutes = opts.minutes("minutes")?;
+            let end = opts.value("end").map(str::to_string);
+            if minutes.is_some() == end.is_some() {
+                return Err("pomo log needs either --minutes <min> or --end <time>".to_string());
+            }
+            Command::Log {
+                project,
+                start,
+                minutes,
+                end,
+            }
+        }
+        "undo" => {
+            let opts = Options::parse(rest, &[], &["yes"])?;
+            opts.no_positionals("undo")?;
+            Command::Undo {
+                yes: opts.flag("yes"),
+            }
+        }
+        "presets" => {
+            Options::parse(rest, &[], &[])?.no_positionals("presets")?;
+            Command::Presets
+        }
+        "hooks" => {
+            Options::parse(rest, &[], &[])?.no_positionals("hooks")?;
+            Command::Hooks
+        }
+        "config" => {
+            let opts = Options::parse(rest, &[], &["unset"])?;
+            let unset = opts.flag("unset");
+            let (key, value) = match opts.positionals.as_slice() {
+                [] if !unset => (None, None),
+                [key] => (Some(key.clone()), None),
+                [key, value] if !unset => (Some(key.clone()), Some(value.clone())),
+                _ => return Err("Usage: pomo config [<key> [<value> | --unset]]".to_string()),
+            };
+            Command::Config { key, value, unset }
+        }
+        "daemon" => {
+            Options::parse(rest, &[], &[])?.no_positionals("daemon")?;
+            Command::Daemon
+        }
+        "ctl" => Command::Ctl(parse_control(rest)?),
+        other => return Err(format!("Unknown command '{}'. Try 'pomo help'.", other)),
+    };
+    Ok(command)
+}
+
+fn parse_control(args: &[String]) -> Result<Control, String> {
+    let Some((action, rest)) = args.split_first() else {
+        return Err("Usage: pomo ctl <action>. Try 'pomo help'.".to_string());
+    };
+    let control = match action.as_str() {
+        "start" => {
+            let opts = Options::parse(rest, &["preset"], &["flow"])?;
+            Control::Start {
+                project: opts.single_positional("ctl start", "<project>")?,
+                preset: opts.value("preset").map(str::to_string),
+                flow: opts.flag("flow"),
+            }
+        }
+        "switch" => {
+            let opts = Options::parse(rest, &[], &[])?;
+            Control::Switch {
+                project: opts.single_positional("ctl switch", "<project>")?,
+            }
+        }
+        simple => {
+            let control = match simple {
+                "pause" => Control::Pause,
+                "resume" => Control::Resume,
+                "skip" => Control::Skip,
+                "stop" => Control::Stop,
+                "status" => Control::Status,
+                other => {
+                    return Err(format!("Unknown ctl action '{}'. Try 'pomo help'.", other));
+                }
+            };
+            Options::parse(rest, &[], &[])?.no_positionals(&format!("ctl {}", simple))?;
+            control
+        }
+    };
+    Ok(control)
+}
+
+// positionals plus --flag / --name value / --name=value options of one subcommand
+struct Options {
+    positionals: Vec<String>,
+    values: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String], value_names: &[&str], flag_names: &[&str]) -> Result<Self, String> {
+        let mut options = Options {
+            positionals: Vec::new(),
+            values: Vec::new(),
+            flags: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                if arg == "-y" && flag_names.contains(&"yes") {
+                    options.flags.push("yes".to_string());
+                } else {
+                    options.positionals.push(arg.clone());
+                }
+                continue;
+            };
+            let (name, inline_value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (option, None),
+            };
+            if value_names.contains(&name) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .ok_or(format!("--{} needs a value", name))?
+                        .clone(),
+                };
+                options.values.push((name.to_string(), value));
+            } else if flag_names.contains(&name) && inline_value.is_none() {
+                options.flags.push(name.to_string());
+            } else {
+                return Err(format!("Unknown option '{}'. Try 'pomo help'.", arg));
+            }
+        }
+        Ok(options)
+    }
+    fn single_positional(&self, command: &str, placeholder: &str) -> Result<String, String> {
+        match self.positionals.as_slice() {
+            [value] => Ok(value.clone()),
+            _ => Err(format!("Usage: pomo {} {}", command, placeholder)),
+        }
+    }
+    fn no_positionals(&self, command: &str) -> Result<(), String> {
+        match self.positionals.first() {
+            Some(extra) => Err(format!("pomo {} takes no argument '{}'", command, extra)),
+            None => Ok(()),
+        }
+    }
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+    fn format(&self) -> Result<Format, String> {
+        match self.value("format") {
+            Some(name) => Format::parse(name),
+            None if self.flag("json") => Ok(Format::Json),
+            None => Ok(Format::Text),
+        }
+    }
+    fn count(&self, name: &str) -> Result<Option<u64>, String> {
+        match self.value(name) {
+            None => Ok(None),
+            Some(value) => match value.parse::<u64>() {
+                Ok(count) if count > 0 => Ok(Some(count)),
+                _ => Err(format!(
+                    "--{} needs a whole number above 0, got '{}'",
+                    name, value
+                )),
+            },
+        }
+    }
+    fn minutes(&self, name: &str) -> Result<Option<f32>, String> {
+        match self.value(name) {
+            None => Ok(None),
+            Some(value) => match value.parse::<f32>() {
+                // also turns away inf and NaN
+                Ok(minutes) if minutes > 0.0 && minutes <= MAX_MINUTES => Ok(Some(minutes)),
+                _ => Err(format!(
+                    "--{} needs a number of minutes above 0 and at most {} (24 hours), got '{}'",
+                    name, MAX_MINUTES, value
+                )),
+            },
+        }
+    }
+}
@@ -1,7 +1,15 @@
+use crate::config;
+use crate::hooks;
+use crate::notify::Notifications;
 use crate::pomodoro;
-use crate::tracker::ProjectTrackerDb;
+use crate::session::{Event, Input, Session, SystemClock, TimerChanges};
+use crate::terminal::Terminal;
+use crate::tracker::{
+    MAX_MINUTES, Phase, Preset, ProjectTimerDefaults, ProjectTracker, ProjectTrackerDb,
+    SessionEntry, SessionOutcome, TimerSettings, unix_now,
+};
 use colored::*;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{self, KeyCode, KeyEventKind};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use std::{
     io,
@@ -19,15 +27,22 @@ pub fn welcome() {
         "Pomodoro".red().bold(),
         "WoftoM".green().italic()
     );
-    println!("");
+    println!();
 }
 pub enum Action {
     Pomodoro,
     CreateNewProject,
-    DeleteProject,
+    ArchiveProject,
     EasterEgg,
     ShowStatus,
     WriteJounal,
+    RenameProject,
+    RestoreProject,
+    PurgeProject,
+    LogSession,
+    EditSessions,
+    UndoLastLog,
+    ManagePresets,
     Exit,
 }
 pub fn action_selection() -> Action {
@@ -35,23 +50,57 @@ pub fn action_selection() -> Action {
         println!("What's your {} move?", "NEXT".bright_yellow());
         println!("{}. Start a {} session", "1".yellow(), "POMODORO".red());
         println!("{}. Create a {} project", "2".yellow(), "NEW".green());
-        println!("{}. Delete an {} project", "3".yellow(), "OLD".blue());
+        println!("{}. Archive an {} project", "3".yellow(), "OLD".blue());
         println!("{}. Show your {}", "4".yellow(), "STATUS".bright_magenta());
         println!("{}. Project {}", "5".yellow(), "JOURNALING".bright_black());
-        println!("{}. {} the tracker", "6".yellow(), "EXIT".cyan());
-        let mut selection = String::new();
-        io::stdin()
-            .read_line(&mut selection)
-            .expect("Failed to read line");
+        println!("{}. {} a project", "6".yellow(), "RENAME".bright_blue());
+        println!(
+            "{}. {} an archived project",
+            "7".yellow(),
+            "RESTORE".bright_green()
+        );
+        println!(
+            "{}. {} an archived project for good",
+            "8".yellow(),
+            "PURGE".bright_red()
+        );
+        println!(
+            "{}. {} focus time spent away from the timer",
+            "9".yellow(),
+            "LOG".bright_yellow()
+        );
+        println!(
+            "{}. {} recorded sessions",
+            "10".yellow(),
+            "EDIT".bright_purple()
+        );
+        println!(
+            "{}. {} the last recorded focus block",
+            "11".yellow(),
+            "UNDO".bright_red()
+        );
+        println!(
+            "{}. Manage timer {}",
+            "12".yellow(),
+            "PRESETS".bright_cyan()
+        );
+        println!("{}. {} the tracker", "13".yellow(), "EXIT".cyan());
         // convert selection to Action
-        match selection.trim().parse::<u8>() {
+        match read_trimmed_line().parse::<u8>() {
             Ok(1) => return Action::Pomodoro,
             Ok(2) => return Action::CreateNewProject,
-            Ok(3) => return Action::DeleteProject,
+            Ok(3) => return Action::ArchiveProject,
             Ok(4) => return Action::ShowStatus,
             Ok(5) => return Action::WriteJounal,
-            Ok(6) => return Action::Exit,
-            Ok(7) => return Action::EasterEgg,
+            Ok(6) => return Action::RenameProject,
+            Ok(7) => return Action::RestoreProject,
+            Ok(8) => return Action::PurgeProject,
+            Ok(9) => return Action::LogSession,
+            Ok(10) => return Action::EditSessions,
+            Ok(11) => return Action::UndoLastLog,
+            Ok(12) => return Action::ManagePresets,
+            Ok(13) => return Action::Exit,
+            Ok(14) => return Action::EasterEgg,
             _ => {
                 println!("Invalid selection. Please try again.");
                 continue;
@@ -69,7 +118,7 @@ pub fn action_router(action: Action, project_tracker_data: &ProjectTrackerDb) {
                     focus_completion_sound();
                 }
                 Ok(false) => {
-                    println!("");
+                    println!();
                 }
                 Err(e) => {
                     println!("{}", e);
@@ -82,7 +131,7 @@ pub fn action_router(action: Action, project_tracker_data: &ProjectTrackerDb) {
                     println!("Project created! ✅")
                 }
                 Ok(false) => {
-                    println!("");
+                    println!();
                 }
                 Err(e) => {
                     println!("{}", e)
@@ -93,13 +142,13 @@ pub fn action_router(action: Action, project_tracker_data: &ProjectTrackerDb) {
             // Easter egg action
             easter_egg();
         }
-        Action::DeleteProject => {
-            match delete_project(project_tracker_data) {
+        Action::ArchiveProject => {
+            match archive_project(project_tracker_data) {
                 Ok(true) => {
-                    println!("Project deleted! ✅")
+                    println!("Project archived! ✅ Restore it any time from the menu.")
                 }
                 Ok(false) => {
-                    println!("");
+                    println!();
                 }
                 Err(e) => {
                     println!("{}", e)
@@ -123,14 +172,106 @@ pub fn action_router(action: Action, project_tracker_data: &ProjectTrackerDb) {
                 println!("Keep Journaling! It's a good habit!");
             }
             Ok(false) => {
-                println!("");
+                println!();
             }
             Err(e) => {
                 println!("{}", e);
             }
         },
+        Action::RenameProject => {
+            match rename_project(project_tracker_data) {
+                Ok(true) => {
+                    println!("Project renamed! ✅")
+                }
+                Ok(false) => {
+                    println!();
+                }
+                Err(e) => {
+                    println!("{}", e)
+                }
+            };
+        }
+        Action::RestoreProject => {
+            match restore_project(project_tracker_data) {
+                Ok(true) => {
+                    println!("Project restored! ✅")
+                }
+                Ok(false) => {
+                    println!();
+                }
+                Err(e) => {
+                    println!("{}", e)
+                }
+            };
+        }
+        Action::PurgeProject => {
+            match purge_project(project_tracker_data) {
+                Ok(true) => {
+                    println!("Project purged for good! ✅")
+                }
+                Ok(false) => {
+                    println!();
+                }
+                Err(e) => {
+                    println!("{}", e)
+                }
+            };
+        }
+        Action::LogSession => {
+            match log_session(project_tracker_data) {
+                Ok(true) => {
+                    println!("Session logged! ✅")
+                }
+                Ok(false) => {
+                    println!();
+                }
+                Err(e) => {
+                    println!("{}", e)
+                }
+            };
+        }
+        Action::EditSessions => {
+            match edit_sessions(project_tracker_data) {
+                Ok(true) => {
+                    println!("Session updated! ✅")
+                }
+                Ok(false) => {
+                    println!();
+                }
+                Err(e) => {
+                    println!("{}", e)
+                }
+            };
+        }
+        Action::UndoLastLog => {
+            match undo_last_log(project_tracker_data, false) {
+                Ok(true) => {
+                    println!("Undone! ✅ A copy is kept in the audit log.")
+                }
+                Ok(false) => {
+                    println!();
+                }
+                Err(e) => {
+                    println!("{}", e)
+                }
+            };
+        }
+        Action::ManagePresets => {
+            match manage_presets(project_tracker_data) {
+                Ok(true) => {
+                    println!("Presets updated! ✅")
+                }
+                Ok(false) => {
+                    println!();
+                }
+                Err(e) => {
+                    println!("{}", e)
+                }
+            };
+        }
         Action::Exit => {
             println!("Goodbye! 🍅");
+            hooks::finish();
             exit(0);
         }
     };
@@ -156,6 +297,7 @@ pub fn easter_egg() {
     println!("{}", ascii_art.bright_cyan())
 }
 pub fn focus_mode(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
+    ensure_no_running_timer(project_tracker_data)?;
     let project_list = match pomodoro::get_all_project(project_tracker_data) {
         Ok(result) => result,
         Err(e) => {
@@ -163,7 +305,7 @@ pub fn focus_mode(project_tracker_data: &ProjectTrackerDb) -> Result<bool, Strin
         }
     };
     if project_list.is_empty() {
-        return Err(format!("No Project to focus. You may create a new one."));
+        return Err("No Project to focus. You may create a new one.".to_string());
     }
     println!(
         "Please {} project for your time! (type 'cancel' to abort)",
@@ -172,74 +314,38 @@ pub fn focus_mode(project_tracker_data: &ProjectTrackerDb) -> Result<bool, Strin
     for (id, project) in project_list.iter().enumerate() {
         println!("{}: {}", id, project.name_getter().trim());
     }
-    let project_index = loop {
-        let mut project_selection = String::new();
-        io::stdin()
-            .read_line(&mut project_selection)
-            .expect("Failed to read line");
-
-        // Check for cancellation
-        if project_selection.trim().eq_ignore_ascii_case("cancel") {
-            println!("Focus session cancelled! 🚫");
-            return Ok(false);
-        }
-
-        match project_selection.trim().parse::<u8>() {
-            Ok(index) if usize::from(index) < project_list.len() => {
-                break usize::from(index);
-            }
-            Ok(_) => {
-                println!("Invalid project index. Please try again.");
-            }
-            Err(_) => {
-                println!("Please type in your desired project's id or 'cancel' to abort.");
-            }
-        }
+    let Some(project_index) = select_project_index(&project_list) else {
+        println!("Focus session cancelled! 🚫");
+        return Ok(false);
     };
 
     let project_name = project_list[project_index].name_getter().trim().to_string();
 
-    let focus_time = match prompt_for_minutes(
-        "How many MINUTES would you like to focus for? (type 'cancel' to abort)",
-    ) {
-        Some(value) => value,
-        None => {
-            println!("Focus session cancelled! 🚫");
-            return Ok(false);
-        }
-    };
-
-    let rest_time = match prompt_for_minutes(
-        "How many MINUTES should each rest be? (type 'cancel' to abort)",
-    ) {
-        Some(value) => value,
-        None => {
-            println!("Focus session cancelled! 🚫");
-            return Ok(false);
-        }
+    let defaults = config::project_timer_settings(project_tracker_data, &project_name)?;
+    let own_defaults =
+        pomodoro::get_project_timer_defaults(project_tracker_data, &project_name)?.is_some();
+    let default_label = if own_defaults {
+        "the project's default"
+    } else {
+        "the default"
     };
-
-    let long_rest_time = match prompt_for_minutes(
-        "How many MINUTES should the long rest be (after 3 cycles)? (type 'cancel' to abort)",
-    ) {
-        Some(value) => value,
-        None => {
-            println!("Focus session cancelled! 🚫");
-            return Ok(false);
-        }
+    let Some(settings) = choose_timer_settings(project_tracker_data, defaults, default_label)?
+    else {
+        println!("Focus session cancelled! 🚫");
+        return Ok(false);
     };
+    let chosen = ProjectTimerDefaults::from_settings(&settings);
+    if chosen != ProjectTimerDefaults::from_settings(&defaults)
+        && confirm(&format!(
+            "Save these as the default for '{}'? Otherwise they are for this session only.",
+            project_name
+        ))
+    {
+        pomodoro::set_project_timer_defaults(project_tracker_data, &project_name, Some(&chosen))?;
+        println!("Saved as the project's default! ✅");
+    }
 
-    println!("");
-    println!(
-        "{}ing on project '{}' for {} minutes...",
-        "FOCUS".green(),
-        project_name,
-        focus_time
-    );
-    println!("Rest intervals set to {} minutes.", rest_time);
-    println!("Long rest (every 3 cycles) set to {} minutes.", long_rest_time);
     println!("Press ENTER to start (or type 'cancel' to abort). ");
-    println!("Controls during timers: 'p' to pause/resume, 's' to skip, 'q' to stop the session.");
 
     let mut start_input = String::new();
     io::stdin()
@@ -251,146 +357,334 @@ pub fn focus_mode(project_tracker_data: &ProjectTrackerDb) -> Result<bool, Strin
         return Ok(false);
     }
 
-    let focus_seconds = (focus_time * 60.0) as u64;
-    let rest_seconds = (rest_time * 60.0) as u64;
-    let long_rest_seconds = (long_rest_time * 60.0) as u64;
-    let mut cycle_count = 1usize;
-    let mut rest_count = 0usize;
-    let mut total_tracked_minutes = 0.0f32;
-    let mut any_tracked = false;
+    run_focus_session(project_tracker_data, &project_name, &settings)
+}
 
+// timer lengths for one session: the default, a preset, or typed in by hand
+fn choose_timer_settings(
+    project_tracker_data: &ProjectTrackerDb,
+    defaults: TimerSettings,
+    default_label: &str,
+) -> Result<Option<TimerSettings>, String> {
+    let presets = pomodoro::get_presets(project_tracker_data)?;
+    println!(
+        "Pick a {} for the timers (type 'custom' to set your own, 'flow' for {}, 'cancel' to abort):",
+        "PRESET".blue(),
+        "FLOWTIME".bright_magenta()
+    );
+    println!(
+        "ENTER: {} ({}, long rest every {} cycles)",
+        default_label,
+        describe_lengths(
+            defaults.focus_seconds,
+            defaults.rest_seconds,
+            defaults.long_rest_seconds
+        ),
+        defaults.long_break_interval
+    );
+    for (id, preset) in presets.iter().enumerate() {
+        println!("{}: {}", id, describe_preset(preset));
+    }
     loop {
-        println!("");
-        println!(
-            "{} Cycle {} — Focus",
-            "Starting".bright_green(),
-            cycle_count
-        );
-        let focus_outcome = run_timer("Focus", focus_seconds)?;
-        let focus_minutes_logged = focus_outcome.elapsed_seconds as f32 / 60.0;
-        if focus_minutes_logged > 0.0 {
-            pomodoro::focus_on_project(project_tracker_data, &project_name, focus_minutes_logged)?;
-            total_tracked_minutes += focus_minutes_logged;
-            any_tracked = true;
+        let input = read_trimmed_line();
+        if input.is_empty() {
+            return Ok(Some(defaults));
         }
-
-        if focus_outcome.quit {
-            println!("{} session ended during focus.", "Focus".yellow());
-            break;
+        if input.eq_ignore_ascii_case("cancel") {
+            return Ok(None);
         }
-
-        if focus_outcome.skipped {
-            println!("Focus skipped. Moving directly to rest.");
-        } else {
-            println!("{} Focus complete! Time to rest.", "✔".green());
-            focus_completion_sound();
+        if input.eq_ignore_ascii_case("flow") {
+            return Ok(Some(TimerSettings {
+                flow: true,
+                ..defaults
+            }));
         }
-
-        println!("{} Cycle {} — Rest", "Starting".cyan(), cycle_count);
-        let rest_outcome = run_timer("Rest", rest_seconds)?;
-
-        if rest_outcome.quit {
-            println!("{} session ended during rest.", "Focus".yellow());
-            break;
-        }
-
-        if rest_outcome.skipped {
-            println!("Rest skipped. Back to focus.");
-        } else {
-            println!("{} Rest complete!", "✔".green());
-            rest_completion_sound();
-            rest_count += 1;
+        if input.eq_ignore_ascii_case("custom") {
+            let Some(preset) = prompt_for_lengths(&defaults, None) else {
+                return Ok(None);
+            };
+            let mut settings = defaults;
+            config::apply_preset(&mut settings, &preset);
+            let Some(interval) = prompt_for_count(
+                "How many cycles until the long rest?",
+                defaults.long_break_interval,
+            ) else {
+                return Ok(None);
+            };
+            settings.long_break_interval = interval;
+            return Ok(Some(settings));
         }
-
-        // Check if it's time for a long rest (after 3 regular rests)
-        if rest_count == 3 {
-            println!("");
-            println!("{} Time for a long rest!", "🌟".bright_yellow());
-            println!("{} Long Rest", "Starting".bright_cyan());
-            let long_rest_outcome = run_timer("Long Rest", long_rest_seconds)?;
-
-            if long_rest_outcome.quit {
-                println!("{} session ended during long rest.", "Focus".yellow());
-                break;
-            }
-
-            if long_rest_outcome.skipped {
-                println!("Long rest skipped. Back to focus.");
-            } else {
-                println!("{} Long rest complete! Ready for more focus!", "✔".green());
-                rest_completion_sound();
+        match input.parse::<usize>() {
+            Ok(index) if index < presets.len() => {
+                let mut settings = defaults;
+                config::apply_preset(&mut settings, &presets[index]);
+                return Ok(Some(settings));
             }
-
-            rest_count = 0; // Reset the rest counter
+            _ => println!(
+                "Please type a preset's id, 'custom', 'flow', or press ENTER for the default."
+            ),
         }
-
-        cycle_count += 1;
-    }
-
-    if any_tracked {
-        println!("Total focus recorded: {:.2} minutes", total_tracked_minutes);
     }
+}
 
-    Ok(any_tracked)
+// the three timer lengths, asked one by one; ENTER keeps those of `current`
+// when there is one. None when cancelled
+fn prompt_for_lengths(defaults: &TimerSettings, current: Option<&Preset>) -> Option<Preset> {
+    let ask = |question: &str, kept: Option<u64>| {
+        let prompt = match kept {
+            Some(seconds) => format!(
+                "{} (ENTER keeps {}, type 'cancel' to abort)",
+                question,
+                format_duration(Duration::from_secs(seconds))
+            ),
+            None => format!("{} (type 'cancel' to abort)", question),
+        };
+        prompt_for_minutes(&prompt, kept)
+    };
+    let focus_seconds = ask(
+        "How many MINUTES would you like to focus for?",
+        current.map(|preset| preset.focus_seconds),
+    )?;
+    let rest_seconds = ask(
+        "How many MINUTES should each rest be?",
+        current.map(|preset| preset.rest_seconds),
+    )?;
+    let long_rest_seconds = ask(
+        &format!(
+            "How many MINUTES should the long rest be (every {} cycles)?",
+            defaults.long_break_interval
+        ),
+        current.map(|preset| preset.long_rest_seconds),
+    )?;
+    Some(Preset {
+        name: String::new(),
+        focus_seconds,
+        rest_seconds,
+        long_rest_seconds,
+    })
 }
 
-fn prompt_for_minutes(prompt: &str) -> Option<f32> {
-    println!("{}", prompt);
+// a whole number above zero; ENTER keeps `current`, None when cancelled
+fn prompt_for_count(prompt: &str, current: u64) -> Option<u64> {
+    println!(
+        "{} (ENTER keeps {}, type 'cancel' to abort)",
+        prompt, current
+    );
     loop {
-        let mut time_input = String::new();
-        io::stdin()
-            .read_line(&mut time_input)
-            .expect("Failed to read line");
-
-        if time_input.trim().eq_ignore_ascii_case("cancel") {
+        let input = read_trimmed_line();
+        if input.is_empty() {
+            return Some(current);
+        }
+        if input.eq_ignore_ascii_case("cancel") {
             return None;
         }
-
-        match time_input.trim().parse::<f32>() {
-            Ok(minutes) if minutes > 0.0 => {
-                return Some(minutes);
-            }
-            Ok(_) => {
-                println!("Please enter a positive number of minutes.");
-            }
-            Err(_) => {
-                println!("Please enter a valid number of minutes.");
-            }
+        match input.parse::<u64>() {
+            Ok(count) if count > 0 => return Some(count),
+            _ => println!("Please enter a whole number above 0."),
         }
     }
 }
 
-struct RawModeGuard;
+fn describe_lengths(focus_seconds: u64, rest_seconds: u64, long_rest_seconds: u64) -> String {
+    format!(
+        "{} focus / {} rest / {} long rest",
+        format_duration(Duration::from_secs(focus_seconds)),
+        format_duration(Duration::from_secs(rest_seconds)),
+        format_duration(Duration::from_secs(long_rest_seconds))
+    )
+}
 
-impl RawModeGuard {
-    fn new() -> Result<Self, String> {
-        enable_raw_mode().map_err(|e| format!("Failed to enable raw mode: {}", e))?;
-        Ok(Self)
-    }
+pub fn describe_preset(preset: &Preset) -> String {
+    format!(
+        "{} — {}",
+        preset.name,
+        describe_lengths(
+            preset.focus_seconds,
+            preset.rest_seconds,
+            preset.long_rest_seconds
+        )
+    )
 }
 
-impl Drop for RawModeGuard {
-    fn drop(&mut self) {
-        let _ = disable_raw_mode();
+// create, edit, delete presets or pick the default one
+pub fn manage_presets(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
+    let presets = pomodoro::get_presets(project_tracker_data)?;
+    let default_name = pomodoro::get_setting(project_tracker_data, "default_preset")?;
+    println!("Your timer {}:", "PRESETS".blue());
+    for (id, preset) in presets.iter().enumerate() {
+        let marker = if default_name.as_deref() == Some(preset.name.as_str()) {
+            " (default)".green().to_string()
+        } else {
+            String::new()
+        };
+        println!("{}: {}{}", id, describe_preset(preset), marker);
+    }
+    println!("What do you want to do?");
+    println!("{}. {} a preset", "1".yellow(), "CREATE".green());
+    println!("{}. {} a preset", "2".yellow(), "EDIT".blue());
+    println!("{}. {} a preset", "3".yellow(), "DELETE".red());
+    println!("{}. Make one the {}", "4".yellow(), "DEFAULT".cyan());
+    println!("(type 'cancel' to abort)");
+    let operation = loop {
+        let input = read_trimmed_line();
+        if input.eq_ignore_ascii_case("cancel") {
+            println!("Presets left as they were. 🚫");
+            return Ok(false);
+        }
+        match input.parse::<u8>() {
+            Ok(choice @ 1..=4) => break choice,
+            _ => println!("Invalid selection. Please try again."),
+        }
+    };
+    if operation == 1 {
+        println!(
+            "What's the preset's {}, or type 'cancel' to abort: ",
+            "NAME".cyan()
+        );
+        let name = read_trimmed_line();
+        if name.eq_ignore_ascii_case("cancel") {
+            println!("Presets left as they were. 🚫");
+            return Ok(false);
+        }
+        let defaults = config::timer_settings(project_tracker_data)?;
+        let Some(preset) = prompt_for_lengths(&defaults, None) else {
+            println!("Presets left as they were. 🚫");
+            return Ok(false);
+        };
+        return pomodoro::create_preset(project_tracker_data, &Preset { name, ..preset });
+    }
+
+    if presets.is_empty() {
+        return Err("There are no presets yet. You may create one.".to_string());
+    }
+    println!(
+        "{} the preset by its id (type 'cancel' to abort):",
+        "SELECT".blue()
+    );
+    let Some(index) = select_index(presets.len(), "preset") else {
+        println!("Presets left as they were. 🚫");
+        return Ok(false);
+    };
+    let preset = &presets[index];
+    match operation {
+        2 => {
+            println!(
+                "New {} (ENTER keeps '{}', 'cancel' aborts): ",
+                "NAME".cyan(),
+                preset.name
+            );
+            let name = read_trimmed_line();
+            if name.eq_ignore_ascii_case("cancel") {
+                println!("Presets left as they were. 🚫");
+                return Ok(false);
+            }
+            let name = if name.is_empty() {
+                preset.name.clone()
+            } else {
+                name
+            };
+            println!(
+                "Now: {}.",
+                describe_lengths(
+                    preset.focus_seconds,
+                    preset.rest_seconds,
+                    preset.long_rest_seconds
+                )
+            );
+            let defaults = config::timer_settings(project_tracker_data)?;
+            let Some(lengths) = prompt_for_lengths(&defaults, Some(preset)) else {
+                println!("Presets left as they were. 🚫");
+                return Ok(false);
+            };
+            pomodoro::update_preset(
+                project_tracker_data,
+                &preset.name,
+                &Preset { name, ..lengths },
+            )
+        }
+        3 => {
+            if confirm(&format!("Delete the preset '{}'?", preset.name)) {
+                pomodoro::delete_preset(project_tracker_data, &preset.name)
+            } else {
+                println!("Presets left as they were. 🚫");
+                Ok(false)
+            }
+        }
+        4 => {
+            config::set(project_tracker_data, "default_preset", &preset.name)?;
+            Ok(true)
+        }
+        _ => unreachable!(),
     }
 }
 
-struct TimerOutcome {
-    elapsed_seconds: u64,
-    quit: bool,
-    skipped: bool,
+// the focus/rest loop itself, shared by the menu and `pomo start`
+pub fn run_focus_session(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+    settings: &TimerSettings,
+) -> Result<bool, String> {
+    println!();
+    if settings.flow {
+        println!(
+            "{}ing on project '{}' in {} mode...",
+            "FOCUS".green(),
+            project_name,
+            "FLOWTIME".bright_magenta()
+        );
+    } else {
+        println!(
+            "{}ing on project '{}' for {}...",
+            "FOCUS".green(),
+            project_name,
+            format_duration(Duration::from_secs(settings.focus_seconds))
+        );
+    }
+    print_session_plan(settings);
+    let session = Session::new(SystemClock, project_name, settings)
+        .with_break_rule(config::break_rule(project_tracker_data)?);
+    hooks::session_started(project_tracker_data, project_name, settings);
+    drive_session(project_tracker_data, session)
 }
 
-fn run_timer(label: &str, total_seconds: u64) -> Result<TimerOutcome, String> {
-    if total_seconds == 0 {
-        return Ok(TimerOutcome {
-            elapsed_seconds: 0,
-            quit: false,
-            skipped: false,
-        });
+fn print_session_plan(settings: &TimerSettings) {
+    if settings.flow {
+        println!("The focus timer counts up: press 's' when you lose flow.");
+        println!("Each break is worked out from how long you focused.");
+        if let Some(target) = settings.target_cycles {
+            println!("The session ends on its own after {} cycles.", target);
+        }
+        println!(
+            "Controls during timers: 'p' to pause/resume, 's' to stop focus or skip a break, 'q' to stop the session."
+        );
+        println!("'+'/'-' add or take off 5 minutes of break, 'z' snoozes it.");
+        return;
     }
+    println!(
+        "Rest intervals set to {}.",
+        format_duration(Duration::from_secs(settings.rest_seconds))
+    );
+    println!(
+        "Long rest (every {} cycles) set to {}.",
+        settings.long_break_interval,
+        format_duration(Duration::from_secs(settings.long_rest_seconds))
+    );
+    if let Some(target) = settings.target_cycles {
+        println!("The session ends on its own after {} cycles.", target);
+    }
+    println!("Controls during timers: 'p' to pause/resume, 's' to skip, 'q' to stop the session.");
+    println!(
+        "'+'/'-' add or take off 5 minutes, 'z' snoozes a break, 'f' finishes focus now as complete."
+    );
+}
 
-    let _raw_mode = RawModeGuard::new()?;
+// the terminal front end of a Session: draws the countdown, turns keys into
+// inputs, records every finished timer and checkpoints the running one
+fn drive_session(
+    project_tracker_data: &ProjectTrackerDb,
+    mut session: Session<SystemClock>,
+) -> Result<bool, String> {
+    let project_name = session.project_name().to_string();
     let spinner_frames: Vec<ColoredString> = vec![
         "⠋".red(),
         "⠙".magenta(),
@@ -403,84 +697,417 @@ fn run_timer(label: &str, total_seconds: u64) -> Result<TimerOutcome, String> {
         "⠇".bright_black(),
         "⠏".bright_red(),
     ];
-
-    let mut remaining = total_seconds;
-    let mut elapsed = 0u64;
     let mut spinner_index = 0usize;
-    let mut paused = false;
-    let mut last_tick = Instant::now();
+    let mut raw_mode: Option<RawModeGuard> = None;
+    let mut last_checkpoint = Instant::now();
+    let mut input: Option<Input> = None;
+    let mut notifications = Notifications::new(project_tracker_data);
+    let mut terminal = Terminal::new(project_tracker_data);
 
     loop {
-        if !paused && last_tick.elapsed() >= Duration::from_secs(1) {
-            if remaining > 0 {
-                remaining -= 1;
-                elapsed += 1;
+        for event in session.update(input.take())? {
+            hooks::session_event(project_tracker_data, &session, &event);
+            notifications.on_event(&session, &event);
+            terminal.on_event(&session, &event, notifications.is_available());
+            match event {
+                Event::PhaseStarted { phase, cycle } => {
+                    match phase {
+                        Phase::Focus => {
+                            println!();
+                            println!("{} Cycle {} — Focus", "Starting".bright_green(), cycle);
+                        }
+                        Phase::Rest => {
+                            println!("{} Cycle {} — Rest", "Starting".cyan(), cycle);
+                            if session.is_flow() {
+                                println!(
+                                    "You earned a {} break.",
+                                    format_duration(Duration::from_secs(session.planned_seconds()))
+                                );
+                            }
+                        }
+                        Phase::LongRest => {
+                            println!();
+                            println!("{} Time for a long rest!", "🌟".bright_yellow());
+                            println!("{} Long Rest", "Starting".bright_cyan());
+                        }
+                    }
+                    pomodoro::save_active_session(project_tracker_data, &session.snapshot())?;
+                    last_checkpoint = Instant::now();
+                    raw_mode = Some(RawModeGuard::new()?);
+                }
+                Event::Paused | Event::Resumed => {
+                    pomodoro::save_active_session(project_tracker_data, &session.snapshot())?;
+                    last_checkpoint = Instant::now();
+                }
+                Event::Adjusted { change, snoozed } => {
+                    let what = if snoozed {
+                        "Snoozed"
+                    } else if change > 0 {
+                        "Extended"
+                    } else {
+                        "Shortened"
+                    };
+                    print!(
+                        "\r\x1B[2K{} by {}, {} planned now.\r\n",
+                        what,
+                        format_duration(Duration::from_secs(change.unsigned_abs())),
+                        format_duration(Duration::from_secs(session.planned_seconds()))
+                    );
+                    pomodoro::save_active_session(project_tracker_data, &session.snapshot())?;
+                    last_checkpoint = Instant::now();
+                }
+                Event::ClockJumped { gap } => {
+                    input = Some(Input::CountSuspended(ask_count_suspended(gap)?));
+                }
+                Event::PhaseEnded(entry) => {
+                    // leave the countdown line before printing in cooked mode
+                    println!();
+                    raw_mode = None;
+                    log_timer(project_tracker_data, &project_name, &entry)?;
+                    pomodoro::clear_active_session(project_tracker_data)?;
+                    // the bell only when no desktop notification says it
+                    print_phase_end(&entry, !notifications.is_available());
+                }
+                Event::Ended { target_reached } => {
+                    if target_reached {
+                        println!("{} All cycles done!", "🎯".bright_green());
+                    }
+                }
             }
-            last_tick = Instant::now();
-        } else if paused {
-            last_tick = Instant::now();
+        }
+        if session.is_ended() {
+            break;
+        }
+        if input.is_some() {
+            continue;
+        }
+
+        if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+            pomodoro::save_active_session(project_tracker_data, &session.snapshot())?;
+            last_checkpoint = Instant::now();
         }
 
-        let minutes = remaining / 60;
-        let seconds = remaining % 60;
-        let spinner = if paused {
+        // a countdown shows whole seconds rounded up, so 25:00 shows first
+        // and 00:00 only at the end; a Flowtime focus counts up instead
+        let (shown, direction) = if session.is_counting_up() {
+            (session.elapsed().as_secs(), "elapsed")
+        } else {
+            (
+                session.remaining().as_millis().div_ceil(1000) as u64,
+                "remaining",
+            )
+        };
+        let minutes = shown / 60;
+        let seconds = shown % 60;
+        let spinner = if session.is_paused() {
             "⏸".yellow().bold()
         } else {
             spinner_frames[spinner_index % spinner_frames.len()].clone()
         };
-
+        let label = match session.phase() {
+            Phase::Focus => "Focus",
+            Phase::Rest => "Rest",
+            Phase::LongRest => "Long Rest",
+        };
+        terminal.update_title(&session);
         print!(
-            "\r\x1B[2K{} {} remaining: {:02}:{:02}",
-            spinner, label, minutes, seconds
+            "\r\x1B[2K{} {} {}: {:02}:{:02}",
+            spinner, label, direction, minutes, seconds
         );
         io::stdout()
             .flush()
             .map_err(|e| format!("Failed to update timer: {}", e))?;
 
-        if remaining == 0 {
-            println!("");
-            return Ok(TimerOutcome {
-                elapsed_seconds: elapsed,
-                quit: false,
-                skipped: false,
-            });
-        }
-
         if event::poll(Duration::from_millis(100))
             .map_err(|e| format!("Failed to read input: {}", e))?
         {
             match event::read().map_err(|e| format!("Failed to read input: {}", e))? {
-                Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                    match key_event.code {
-                        KeyCode::Char('p') | KeyCode::Char('P') => {
-                            paused = !paused;
-                        }
-                        KeyCode::Char('s') | KeyCode::Char('S') => {
-                            println!("");
-                            return Ok(TimerOutcome {
-                                elapsed_seconds: elapsed,
-                                quit: false,
-                                skipped: true,
-                            });
-                        }
-                        KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
-                            println!("");
-                            return Ok(TimerOutcome {
-                                elapsed_seconds: elapsed,
-                                quit: true,
-                                skipped: false,
-                            });
-                        }
-                        _ => {}
-                    }
+                event::Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    input = match key_event.code {
+                        KeyCode::Char('p') | KeyCode::Char('P') => Some(Input::TogglePause),
+                        KeyCode::Char('s') | KeyCode::Char('S') => Some(Input::Skip),
+                        KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => Some(Input::Quit),
+                        KeyCode::Char('+') | KeyCode::Char('=') => Some(Input::Adjust(ADJUST_STEP)),
+                        KeyCode::Char('-') => Some(Input::Adjust(-ADJUST_STEP)),
+                        KeyCode::Char('z') | KeyCode::Char('Z') => Some(Input::Snooze),
+                        KeyCode::Char('f') | KeyCode::Char('F') => Some(Input::FinishNow),
+                        _ => None,
+                    };
                 }
                 _ => {}
             }
         }
+        if input.is_none() {
+            input = notifications.take_input(&session);
+        }
 
         spinner_index = spinner_index.wrapping_add(1);
         thread::sleep(Duration::from_millis(100));
     }
+    drop(raw_mode);
+
+    let total_tracked_seconds = session.focus_seconds();
+    if total_tracked_seconds > 0 {
+        println!(
+            "Total focus recorded: {}",
+            format_duration(Duration::from_secs(total_tracked_seconds))
+        );
+    }
+    print_timer_changes(&session.timer_changes());
+
+    Ok(total_tracked_seconds > 0)
+}
+
+// the extensions, cuts and snoozes of a session, if there were any
+fn print_timer_changes(changes: &TimerChanges) {
+    if changes.is_empty() {
+        return;
+    }
+    let mut parts = Vec::new();
+    if changes.extended_seconds > 0 {
+        parts.push(format!(
+            "extended by {}",
+            format_duration(Duration::from_secs(changes.extended_seconds))
+        ));
+    }
+    if changes.shortened_seconds > 0 {
+        parts.push(format!(
+            "shortened by {}",
+            format_duration(Duration::from_secs(changes.shortened_seconds))
+        ));
+    }
+    if changes.snoozes > 0 {
+        parts.push(format!("{} break(s) snoozed", changes.snoozes));
+    }
+    if changes.finished_early > 0 {
+        parts.push(format!(
+            "{} focus(es) finished early",
+            changes.finished_early
+        ));
+    }
+    println!("Timer changes: {}", parts.join(", "));
+}
+
+fn print_phase_end(entry: &SessionEntry, ring: bool) {
+    match (entry.phase, entry.outcome) {
+        (Phase::Focus, SessionOutcome::Quit) => {
+            println!("{} session ended during focus.", "Focus".yellow())
+        }
+        (Phase::Focus, SessionOutcome::Skipped) => {
+            println!("Focus skipped. Moving directly to rest.")
+        }
+        (Phase::Focus, SessionOutcome::Completed) => {
+            println!("{} Focus complete! Time to rest.", "✔".green());
+            if ring {
+                focus_completion_sound();
+            }
+        }
+        (Phase::Rest, SessionOutcome::Quit) => {
+            println!("{} session ended during rest.", "Focus".yellow())
+        }
+        (Phase::Rest, SessionOutcome::Skipped) => println!("Rest skipped. Back to focus."),
+        (Phase::Rest, SessionOutcome::Completed) => {
+            println!("{} Rest complete!", "✔".green());
+            if ring {
+                rest_completion_sound();
+            }
+        }
+        (Phase::LongRest, SessionOutcome::Quit) => {
+            println!("{} session ended during long rest.", "Focus".yellow())
+        }
+        (Phase::LongRest, SessionOutcome::Skipped) => {
+            println!("Long rest skipped. Back to focus.")
+        }
+        (Phase::LongRest, SessionOutcome::Completed) => {
+            println!("{} Long rest complete! Ready for more focus!", "✔".green());
+            if ring {
+                rest_completion_sound();
+            }
+        }
+    }
+}
+
+// asked in raw mode after the clock jumped ahead by `gap`
+fn ask_count_suspended(gap: Duration) -> Result<bool, String> {
+    print!(
+        "\r\x1B[2K{} The clock jumped ahead by {}; was the computer asleep?\r\nCount that time toward this timer? (y/N) ",
+        "⏰".bright_yellow(),
+        format_duration(gap)
+    );
+    io::stdout()
+        .flush()
+        .map_err(|e| format!("Failed to update timer: {}", e))?;
+    loop {
+        match event::read().map_err(|e| format!("Failed to read input: {}", e))? {
+            event::Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                let counted = matches!(key_event.code, KeyCode::Char('y') | KeyCode::Char('Y'));
+                print!("{}\r\n", if counted { "yes" } else { "no" });
+                return Ok(counted);
+            }
+            _ => {}
+        }
+    }
+}
+
+// errs while another pomo (or the daemon) runs a timer: a fresh checkpoint
+// means it is still going, and a second timer would overwrite it
+pub fn ensure_no_running_timer(project_tracker_data: &ProjectTrackerDb) -> Result<(), String> {
+    match pomodoro::get_active_session(project_tracker_data)? {
+        Some(active) if unix_now() - active.updated_at < STALE_AFTER.as_secs() as i64 => {
+            Err(format!(
+                "A {} on '{}' is running in another pomo right now. Stop it there first.",
+                active.phase.as_str(),
+                active.project_name
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+// a session that was still running when pomo last exited: resume it, log
+// the time it got to, or throw it away. Ok(true) means it was resumed; errs
+// when another pomo is running a timer right now.
+pub fn recover_active_session(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
+    ensure_no_running_timer(project_tracker_data)?;
+    let Some(active) = pomodoro::get_active_session(project_tracker_data)? else {
+        return Ok(false);
+    };
+    let last_saved = project_tracker_data
+        .format_local_time(active.updated_at)
+        .map_err(|e| format!("Error formatting time: {}", e))?;
+    println!(
+        "{} An unfinished {} on '{}' was found (last saved {}).",
+        "⚠".bright_yellow(),
+        active.phase.as_str(),
+        active.project_name.bright_yellow(),
+        last_saved
+    );
+    if active.settings.flow && active.phase == Phase::Focus {
+        println!(
+            "It got to {} of Flowtime focus, paused {} time(s).",
+            format_duration(Duration::from_secs(active.elapsed_seconds)),
+            active.pause_count
+        );
+    } else {
+        println!(
+            "It got to {} of {}, paused {} time(s).",
+            format_duration(Duration::from_secs(active.elapsed_seconds)),
+            format_duration(Duration::from_secs(active.planned_seconds())),
+            active.pause_count
+        );
+    }
+    println!("{}. {} the timer", "1".yellow(), "RESUME".green());
+    println!("{}. {} the partial time", "2".yellow(), "LOG".blue());
+    println!("{}. {} it", "3".yellow(), "DISCARD".red());
+    let choice = loop {
+        match read_trimmed_line().parse::<u8>() {
+            Ok(choice @ 1..=3) => break choice,
+            _ => println!("Invalid selection. Please try again."),
+        }
+    };
+    match choice {
+        1 => {
+            println!();
+            println!(
+                "{}ing on project '{}' again...",
+                "FOCUS".green(),
+                active.project_name
+            );
+            print_session_plan(&active.settings);
+            if active.paused {
+                println!("It was paused when it stopped, and still is: press 'p' to go on.");
+            }
+            let session = Session::resume(SystemClock, active)
+                .with_break_rule(config::break_rule(project_tracker_data)?);
+            if drive_session(project_tracker_data, session)? {
+                println!("\r🎉 Focus session completed!                            ");
+            }
+            Ok(true)
+        }
+        2 => {
+            let entry = SessionEntry {
+                phase: active.phase,
+                started_at: active.started_at,
+                ended_at: active.updated_at,
+                elapsed_seconds: active.elapsed_seconds,
+                outcome: SessionOutcome::Quit,
+                adjustment_seconds: active.adjustment_seconds,
+            };
+            log_timer(project_tracker_data, &active.project_name, &entry)?;
+            pomodoro::clear_active_session(project_tracker_data)?;
+            println!("Partial session logged! ✅");
+            Ok(false)
+        }
+        _ => {
+            pomodoro::clear_active_session(project_tracker_data)?;
+            println!("Unfinished session discarded. 🗑️");
+            Ok(false)
+        }
+    }
+}
+
+// asks in minutes, answers in whole seconds; ENTER gives back `kept` if there is one
+fn prompt_for_minutes(prompt: &str, kept: Option<u64>) -> Option<u64> {
+    println!("{}", prompt);
+    loop {
+        let time_input = read_trimmed_line();
+        if time_input.eq_ignore_ascii_case("cancel") {
+            return None;
+        }
+        if let Some(seconds) = kept.filter(|_| time_input.is_empty()) {
+            return Some(seconds);
+        }
+
+        match time_input.parse::<f32>() {
+            Ok(minutes) if minutes > 0.0 && minutes <= MAX_MINUTES => {
+                return Some((minutes * 60.0).round() as u64);
+            }
+            Ok(_) => {
+                println!(
+                    "Please enter a number of minutes above 0 and at most {} (24 hours).",
+                    MAX_MINUTES
+                );
+            }
+            Err(_) => {
+                println!("Please enter a valid number of minutes.");
+            }
+        }
+    }
+}
+
+// how much '+' and '-' add to or take off the running timer, in seconds
+const ADJUST_STEP: i64 = 5 * 60;
+// how often a running timer is written to the database
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+// a checkpoint older than this belongs to a pomo that is no longer running
+pub const STALE_AFTER: Duration = Duration::from_secs(15);
+
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn new() -> Result<Self, String> {
+        enable_raw_mode().map_err(|e| format!("Failed to enable raw mode: {}", e))?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
+
+// write a finished timer run to the session history; untouched timers are not logged
+fn log_timer(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+    entry: &SessionEntry,
+) -> Result<(), String> {
+    if entry.elapsed_seconds == 0 {
+        return Ok(());
+    }
+    pomodoro::record_session(project_tracker_data, project_name, entry)?;
+    Ok(())
 }
 
 pub fn create_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
@@ -494,7 +1121,7 @@ pub fn create_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, S
         .expect("Failed to read line");
     let project_name = project_name.trim();
     if project_name.is_empty() {
-        return Err(format!("Project name cannot be empty.").into());
+        return Err("Project name cannot be empty.".to_string());
     }
     if project_name.eq_ignore_ascii_case("cancel") {
         println!("Project creation cancelled! 🚫");
@@ -502,10 +1129,10 @@ pub fn create_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, S
     }
     pomodoro::create_project(project_tracker_data, project_name)
 }
-pub fn delete_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
+pub fn archive_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
     println!(
         "Which project do you want to {}? Please type in its full name (or 'cancel' to abort).",
-        "DELETE".blue()
+        "ARCHIVE".blue()
     );
     let project_list = match pomodoro::get_all_project(project_tracker_data) {
         Ok(result) => result,
@@ -514,13 +1141,13 @@ pub fn delete_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, S
         }
     };
     if project_list.is_empty() {
-        return Err(format!("No Project to delete."));
+        return Err("No Project to archive.".to_string());
     };
     for project in project_list.iter() {
         println!(
-            "{}: {} minutes",
+            "{}: {}",
             project.name_getter().trim(),
-            project.time_getter()
+            format_duration(project.time_getter())
         );
     }
     let mut project_name = String::new();
@@ -530,14 +1157,373 @@ pub fn delete_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, S
 
     // Check for cancellation
     if project_name.trim().eq_ignore_ascii_case("cancel") {
-        println!("Deletion cancelled! 🚫");
+        println!("Archiving cancelled! 🚫");
+        return Ok(false);
+    }
+
+    pomodoro::archive_project(project_tracker_data, project_name.trim())
+}
+fn print_archived_projects(project_tracker_data: &ProjectTrackerDb) -> Result<(), String> {
+    let project_list = pomodoro::get_archived_projects(project_tracker_data)?;
+    if project_list.is_empty() {
+        return Err("No archived projects.".to_string());
+    }
+    println!("{} projects:", "Archived".bright_black());
+    for project in project_list.iter() {
+        println!(
+            "{}: {}",
+            project.name_getter().trim(),
+            format_duration(project.time_getter())
+        );
+    }
+    Ok(())
+}
+pub fn restore_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
+    print_archived_projects(project_tracker_data)?;
+    println!(
+        "Which project do you want to {}? Please type in its full name (or 'cancel' to abort).",
+        "RESTORE".bright_green()
+    );
+    let mut project_name = String::new();
+    io::stdin()
+        .read_line(&mut project_name)
+        .expect("Failed to read line");
+
+    if project_name.trim().eq_ignore_ascii_case("cancel") {
+        println!("Restoring cancelled! 🚫");
+        return Ok(false);
+    }
+
+    pomodoro::restore_project(project_tracker_data, project_name.trim())
+}
+pub fn purge_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
+    print_archived_projects(project_tracker_data)?;
+    println!(
+        "Which project do you want to {}? Its time, history and journal are deleted permanently. Please type in its full name (or 'cancel' to abort).",
+        "PURGE".bright_red()
+    );
+    let mut project_name = String::new();
+    io::stdin()
+        .read_line(&mut project_name)
+        .expect("Failed to read line");
+    let project_name = project_name.trim();
+
+    if project_name.eq_ignore_ascii_case("cancel") {
+        println!("Purge cancelled! 🚫");
+        return Ok(false);
+    }
+
+    println!(
+        "Type {} to permanently purge '{}':",
+        "yes".bright_red(),
+        project_name
+    );
+    let mut confirmation = String::new();
+    io::stdin()
+        .read_line(&mut confirmation)
+        .expect("Failed to read line");
+    if confirmation.trim() != "yes" {
+        println!("Purge cancelled! 🚫");
         return Ok(false);
     }
 
-    pomodoro::delete_project(project_tracker_data, project_name.trim())
+    pomodoro::purge_project(project_tracker_data, project_name)
+}
+pub fn rename_project(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
+    let project_list = pomodoro::get_all_project(project_tracker_data)?;
+    if project_list.is_empty() {
+        return Err("No Project to rename.".to_string());
+    }
+    println!(
+        "Which project do you want to {}? (type 'cancel' to abort)",
+        "RENAME".bright_blue()
+    );
+    for (id, project) in project_list.iter().enumerate() {
+        println!("{}: {}", id, project.name_getter().trim());
+    }
+    let Some(project_index) = select_project_index(&project_list) else {
+        println!("Renaming cancelled! 🚫");
+        return Ok(false);
+    };
+    let project_name = project_list[project_index].name_getter().trim();
+
+    println!(
+        "What's the project's new {}, or type 'cancel' to abort: ",
+        "NAME".cyan()
+    );
+    let mut new_name = String::new();
+    io::stdin()
+        .read_line(&mut new_name)
+        .expect("Failed to read line");
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err("Project name cannot be empty.".to_string());
+    }
+    if new_name.eq_ignore_ascii_case("cancel") {
+        println!("Renaming cancelled! 🚫");
+        return Ok(false);
+    }
+    pomodoro::rename_project(project_tracker_data, project_name, new_name)
+}
+pub fn log_session(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
+    let project_list = pomodoro::get_all_project(project_tracker_data)?;
+    if project_list.is_empty() {
+        return Err("No Project to log time for. You may create a new one.".to_string());
+    }
+    println!(
+        "Please {} project to log time for! (type 'cancel' to abort)",
+        "SELECT".blue()
+    );
+    for (id, project) in project_list.iter().enumerate() {
+        println!("{}: {}", id, project.name_getter().trim());
+    }
+    let Some(project_index) = select_project_index(&project_list) else {
+        println!("Logging cancelled! 🚫");
+        return Ok(false);
+    };
+    let project_name = project_list[project_index].name_getter().trim();
+
+    println!(
+        "When did you {}? (YYYY-MM-DD HH:MM, or HH:MM for today; 'cancel' to abort)",
+        "START".green()
+    );
+    let started_at = loop {
+        let input = read_trimmed_line();
+        if input.eq_ignore_ascii_case("cancel") {
+            println!("Logging cancelled! 🚫");
+            return Ok(false);
+        }
+        match project_tracker_data.parse_local_time(&input) {
+            Ok(Some(timestamp)) => break timestamp,
+            Ok(None) => println!("Please enter a time like 2024-05-01 14:30 or 14:30."),
+            Err(e) => return Err(format!("Error reading time: {}", e)),
+        }
+    };
+
+    println!(
+        "How many MINUTES did it last, or when did it {}? (HH:MM or YYYY-MM-DD HH:MM; 'cancel' to abort)",
+        "END".red()
+    );
+    let ended_at = loop {
+        let input = read_trimmed_line();
+        if input.eq_ignore_ascii_case("cancel") {
+            println!("Logging cancelled! 🚫");
+            return Ok(false);
+        }
+        if let Ok(minutes) = input.parse::<f32>() {
+            // also turns away inf and NaN
+            if minutes > 0.0
+                && minutes <= MAX_MINUTES
+                && let Some(ended_at) = started_at.checked_add((minutes * 60.0).round() as i64)
+            {
+                break ended_at;
+            }
+            println!(
+                "Please enter a number of minutes above 0 and at most {} (24 hours).",
+                MAX_MINUTES
+            );
+            continue;
+        }
+        match project_tracker_data.parse_local_time(&input) {
+            Ok(Some(timestamp)) => break timestamp,
+            Ok(None) => println!("Please enter a number of minutes or an end time."),
+            Err(e) => return Err(format!("Error reading time: {}", e)),
+        }
+    };
+
+    pomodoro::log_manual_session(project_tracker_data, project_name, started_at, ended_at)
+}
+pub fn edit_sessions(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
+    let project_list = pomodoro::get_all_project(project_tracker_data)?;
+    if project_list.is_empty() {
+        return Err("No Project to edit. You may create a new one.".to_string());
+    }
+    println!(
+        "Please {} project whose sessions you want to edit! (type 'cancel' to abort)",
+        "SELECT".blue()
+    );
+    for (id, project) in project_list.iter().enumerate() {
+        println!("{}: {}", id, project.name_getter().trim());
+    }
+    let Some(project_index) = select_project_index(&project_list) else {
+        println!("Editing cancelled! 🚫");
+        return Ok(false);
+    };
+    let project_name = project_list[project_index].name_getter().trim();
+
+    let sessions = pomodoro::get_project_sessions(project_tracker_data, project_name, 15)?;
+    if sessions.is_empty() {
+        return Err(format!("'{}' has no recorded sessions yet.", project_name));
+    }
+    println!(
+        "Recent sessions of '{}' — {} one (type 'cancel' to abort):",
+        project_name,
+        "SELECT".blue()
+    );
+    for (id, session) in sessions.iter().enumerate() {
+        let started = project_tracker_data
+            .format_local_time(session.entry.started_at)
+            .map_err(|e| format!("Error formatting time: {}", e))?;
+        println!(
+            "{}: {} │ {:<9} │ {:>7} │ {}{}",
+            id,
+            started,
+            session.entry.phase.as_str(),
+            format_duration(Duration::from_secs(session.entry.elapsed_seconds)),
+            session.entry.outcome.as_str(),
+            format_adjustment(session.entry.adjustment_seconds)
+        );
+    }
+    let Some(session_index) = select_index(sessions.len(), "session") else {
+        println!("Editing cancelled! 🚫");
+        return Ok(false);
+    };
+    let session = &sessions[session_index];
+
+    println!("What do you want to do with it?");
+    println!("{}. Adjust its {}", "1".yellow(), "DURATION".green());
+    println!("{}. {} it to another project", "2".yellow(), "MOVE".blue());
+    println!("{}. {} it in two", "3".yellow(), "SPLIT".cyan());
+    println!("{}. {} it", "4".yellow(), "DELETE".red());
+    println!("(type 'cancel' to abort)");
+    let operation = loop {
+        let input = read_trimmed_line();
+        if input.eq_ignore_ascii_case("cancel") {
+            println!("Editing cancelled! 🚫");
+            return Ok(false);
+        }
+        match input.parse::<u8>() {
+            Ok(choice @ 1..=4) => break choice,
+            _ => println!("Invalid selection. Please try again."),
+        }
+    };
+    match operation {
+        1 => {
+            let Some(seconds) = prompt_for_minutes(
+                "How many MINUTES should it last? (type 'cancel' to abort)",
+                None,
+            ) else {
+                println!("Editing cancelled! 🚫");
+                return Ok(false);
+            };
+            pomodoro::adjust_session_duration(project_tracker_data, session.id, seconds)
+        }
+        2 => {
+            let targets: Vec<_> = project_list
+                .iter()
+                .filter(|p| p.name_getter() != project_name)
+                .collect();
+            if targets.is_empty() {
+                return Err("There is no other project to move it to.".to_string());
+            }
+            println!(
+                "{} the project to move it to (type 'cancel' to abort):",
+                "SELECT".blue()
+            );
+            for (id, project) in targets.iter().enumerate() {
+                println!("{}: {}", id, project.name_getter().trim());
+            }
+            let Some(target_index) = select_index(targets.len(), "project") else {
+                println!("Editing cancelled! 🚫");
+                return Ok(false);
+            };
+            pomodoro::move_session(
+                project_tracker_data,
+                session.id,
+                targets[target_index].name_getter(),
+            )
+        }
+        3 => {
+            let Some(seconds) = prompt_for_minutes(
+                "How many MINUTES go into the first part? (type 'cancel' to abort)",
+                None,
+            ) else {
+                println!("Editing cancelled! 🚫");
+                return Ok(false);
+            };
+            pomodoro::split_session(project_tracker_data, session.id, seconds)
+        }
+        4 => {
+            if confirm("Delete this session for good?") {
+                pomodoro::delete_session(project_tracker_data, session.id)
+            } else {
+                println!("Editing cancelled! 🚫");
+                Ok(false)
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+// shows what will be undone and asks first, unless assume_yes
+pub fn undo_last_log(
+    project_tracker_data: &ProjectTrackerDb,
+    assume_yes: bool,
+) -> Result<bool, String> {
+    let Some(session) = pomodoro::get_last_focus_session(project_tracker_data)? else {
+        return Err("Nothing to undo.".to_string());
+    };
+    let started = project_tracker_data
+        .format_local_time(session.entry.started_at)
+        .map_err(|e| format!("Error formatting time: {}", e))?;
+    println!("The last recorded focus block is:");
+    println!(
+        "  {} │ {} │ {} │ {}",
+        session.project_name.bright_yellow(),
+        started,
+        format_duration(Duration::from_secs(session.entry.elapsed_seconds)),
+        session.entry.outcome.as_str()
+    );
+    if !assume_yes && !confirm(&format!("{} it?", "UNDO".bright_red())) {
+        println!("Undo cancelled! 🚫");
+        return Ok(false);
+    }
+    pomodoro::undo_session(project_tracker_data, &session)
+}
+// "2h 5m", "25m 30s", "40s"
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let (hours, minutes, seconds) = (total / 3600, total % 3600 / 60, total % 60);
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 && seconds > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+// how far a recorded timer was lengthened or shortened while it ran, e.g. " (+5m)"
+pub fn format_adjustment(adjustment_seconds: i64) -> String {
+    let length = format_duration(Duration::from_secs(adjustment_seconds.unsigned_abs()));
+    match adjustment_seconds {
+        0 => String::new(),
+        1.. => format!(" (+{})", length),
+        _ => format!(" (-{})", length),
+    }
+}
+// a (y/N) question; anything but 'y' is a no
+pub fn confirm(question: &str) -> bool {
+    println!("{} (y/N)", question);
+    read_trimmed_line().eq_ignore_ascii_case("y")
+}
+// once stdin has run out (closed, or a file or pipe that ended) no answer
+// will ever come, so pomo stops rather than ask again forever; whatever was
+// being asked about is left as it was
+fn read_trimmed_line() -> String {
+    let mut input = String::new();
+    let read = io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+    if read == 0 {
+        eprintln!("No more input; leaving pomo.");
+        hooks::finish();
+        exit(1);
+    }
+    input.trim().to_string()
 }
 pub fn show_status(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
-    println!("");
+    println!();
     println!("Your Focus Dashboard");
     println!("═══════════════════════════════════════");
 
@@ -549,10 +1535,10 @@ pub fn show_status(project_tracker_data: &ProjectTrackerDb) -> Result<bool, Stri
     };
 
     if project_list.is_empty() {
-        return Err(format!("Wow! You haven't focused for even one minute! 😹"));
+        return Err("Wow! You haven't focused for even one minute! 😹".to_string());
     }
 
-    let total_time: f32 = project_list.iter().map(|p| p.time_getter()).sum();
+    let total_time: Duration = project_list.iter().map(|p| p.time_getter()).sum();
     let max_name_len = project_list
         .iter()
         .map(|p| p.name_getter().trim().len())
@@ -562,11 +1548,14 @@ pub fn show_status(project_tracker_data: &ProjectTrackerDb) -> Result<bool, Stri
     for project in project_list.iter() {
         let name = project.name_getter().trim();
         let time = project.time_getter();
-        let percentage = (time as f64 / total_time as f64) * 100.0;
+        let percentage = if total_time.is_zero() {
+            0.0
+        } else {
+            time.as_secs_f64() / total_time.as_secs_f64() * 100.0
+        };
         let bar_length = ((percentage / 100.0) * 30.0) as usize;
 
-        let hours = time / 60.0;
-        let time_str = format!("{}h", hours);
+        let time_str = format_duration(time);
         let bar = "█".repeat(bar_length) + &"░".repeat(30 - bar_length);
 
         println!(
@@ -580,9 +1569,12 @@ pub fn show_status(project_tracker_data: &ProjectTrackerDb) -> Result<bool, Stri
     }
 
     println!("═══════════════════════════════════════");
-    let total_hours = total_time / 60.0;
-    println!("{} Focus Time: {}h", "TOTAL".cyan(), total_hours);
-    println!("");
+    println!(
+        "{} Focus Time: {}",
+        "TOTAL".cyan(),
+        format_duration(total_time)
+    );
+    println!();
     Ok(true)
 }
 pub fn journal_mode(project_tracker_data: &ProjectTrackerDb) -> Result<bool, String> {
@@ -593,7 +1585,7 @@ pub fn journal_mode(project_tracker_data: &ProjectTrackerDb) -> Result<bool, Str
         }
     };
     if project_list.is_empty() {
-        return Err(format!("No journal to write. You may create a new one."));
+        return Err("No journal to write. You may create a new one.".to_string());
     }
     println!(
         "Please {} project for journaling! (type 'cancel' to abort)",
@@ -602,36 +1594,43 @@ pub fn journal_mode(project_tracker_data: &ProjectTrackerDb) -> Result<bool, Str
     for (id, project) in project_list.iter().enumerate() {
         println!("{}: {}", id, project.name_getter().trim());
     }
-    let project_index = loop {
-        let mut project_selection = String::new();
-        io::stdin()
-            .read_line(&mut project_selection)
-            .expect("Failed to read line");
+    let Some(project_index) = select_project_index(&project_list) else {
+        println!("Journaling cancelled! 🚫");
+        return Ok(false);
+    };
+    pomodoro::write_journal(
+        project_tracker_data,
+        project_list[project_index].name_getter().trim(),
+    )
+}
+// read a project id from the numbered list; None when the user cancels
+fn select_project_index(project_list: &[ProjectTracker]) -> Option<usize> {
+    select_index(project_list.len(), "project")
+}
+fn select_index(count: usize, noun: &str) -> Option<usize> {
+    loop {
+        let selection = read_trimmed_line();
 
         // Check for cancellation
-        if project_selection.trim().eq_ignore_ascii_case("cancel") {
-            println!("Journaling cancelled! 🚫");
-            return Ok(false);
+        if selection.eq_ignore_ascii_case("cancel") {
+            return None;
         }
 
-        match project_selection.trim().parse::<u8>() {
-            Ok(index) if usize::from(index) < project_list.len() => {
-                break index;
+        match selection.parse::<usize>() {
+            Ok(index) if index < count => {
+                return Some(index);
             }
             Ok(_) => {
-                println!("Invalid project index. Please try again.");
+                println!("Invalid {} index. Please try again.", noun);
             }
             Err(_) => {
-                println!("Please type in your desired project's id or 'cancel' to abort.");
+                println!(
+                    "Please type in your desired {}'s id or 'cancel' to abort.",
+                    noun
+                );
             }
         }
-    };
-    pomodoro::write_journal(
-        project_tracker_data,
-        project_list[usize::from(project_index)]
-            .name_getter()
-            .trim(),
-    )
+    }
 }
 fn focus_completion_sound() {
     let notes = [(523, 160), (659, 180), (784, 260)];
@@ -647,7 +1646,7 @@ fn play_tones(notes: &[(u32, u64)]) {
     if std::env::consts::OS == "windows" {
         for (freq, duration) in notes.iter() {
             let _ = Command::new("powershell.exe")
-                .args(&["-c", &format!("[Console]::Beep({}, {})", freq, duration)])
+                .args(["-c", &format!("[Console]::Beep({}, {})", freq, duration)])
                 .output();
             thread::sleep(Duration::from_millis(60));
         }
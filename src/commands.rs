@@ -0,0 +1,355 @@
+// runs one subcommand non-interactively, reusing the pomodoro state manager
+use crate::args::Command;
+use crate::cli;
+use crate::config;
+use crate::hooks::{self, Hook};
+use crate::output::{self, Format};
+use crate::pomodoro;
+use crate::statusline;
+use crate::tracker::{ProjectTimerDefaults, ProjectTrackerDb, TimerSettings};
+use std::time::Duration;
+
+pub fn run(command: Command, project_tracker_data: &ProjectTrackerDb) -> Result<(), String> {
+    match command {
+        // main handles these before a database is opened
+        Command::Menu | Command::Help => Ok(()),
+        Command::Start {
+            project,
+            preset,
+            focus_minutes,
+            rest_minutes,
+            long_rest_minutes,
+            long_break_interval,
+            cycles,
+            count_skipped_rests,
+            save_defaults,
+            clear_defaults,
+            flow,
+        } => {
+            let project = pomodoro::get_project(project_tracker_data, &project)?;
+            if cli::recover_active_session(project_tracker_data)? {
+                return Ok(());
+            }
+            if save_defaults && clear_defaults {
+                return Err("Pick one of --save-defaults and --clear-defaults.".to_string());
+            }
+            if clear_defaults {
+                pomodoro::set_project_timer_defaults(
+                    project_tracker_data,
+                    project.name_getter(),
+                    None,
+                )?;
+            }
+            let mut defaults =
+                config::project_timer_settings(project_tracker_data, project.name_getter())?;
+            if let Some(preset) = preset {
+                let preset = pomodoro::get_preset(project_tracker_data, &preset)?;
+                config::apply_preset(&mut defaults, &preset);
+            }
+            let settings = TimerSettings {
+                focus_seconds: to_seconds(focus_minutes).unwrap_or(defaults.focus_seconds),
+                rest_seconds: to_seconds(rest_minutes).unwrap_or(defaults.rest_seconds),
+                long_rest_seconds: to_seconds(long_rest_minutes)
+                    .unwrap_or(defaults.long_rest_seconds),
+                long_break_interval: long_break_interval.unwrap_or(defaults.long_break_interval),
+                target_cycles: cycles.or(defaults.target_cycles),
+                count_skipped_rests: count_skipped_rests || defaults.count_skipped_rests,
+                flow,
+            };
+            if save_defaults {
+                pomodoro::set_project_timer_defaults(
+                    project_tracker_data,
+                    project.name_getter(),
+                    Some(&ProjectTimerDefaults::from_settings(&settings)),
+                )?;
+                println!("Saved as the project's default! ✅");
+            }
+            cli::run_focus_session(project_tracker_data, project.name_getter(), &settings)?;
+            Ok(())
+        }
+        Command::New { name } => {
+            pomodoro::create_project(project_tracker_data, &name)?;
+            println!("Project created! ✅");
+            Ok(())
+        }
+        Command::Status {
+            format,
+            line,
+            template,
+        } => {
+            if let Some(style) = line {
+                if let Some(line) =
+                    statusline::render(project_tracker_data, style, template.as_deref())?
+                {
+                    println!("{}", line);
+                }
+                return Ok(());
+            }
+            if format == Format::Text {
+                cli::show_status(project_tracker_data)?;
+                return Ok(());
+            }
+            let project_list = pomodoro::get_all_project(project_tracker_data)?;
+            println!(
+                "{}",
+                output::render(format, &output::status_document(&project_list))
+            );
+            Ok(())
+        }
+        Command::Projects { archived, format } => {
+            let project_list = if archived {
+                pomodoro::get_archived_projects(project_tracker_data)?
+            } else {
+                pomodoro::get_all_project(project_tracker_data)?
+            };
+            if format != Format::Text {
+                println!(
+                    "{}",
+                    output::render(format, &output::projects_document(&project_list))
+                );
+                return Ok(());
+            }
+            for project in project_list.iter() {
+                println!(
+                    "{}\t{}",
+                    project.name_getter(),
+                    cli::format_duration(project.time_getter())
+                );
+            }
+            Ok(())
+        }
+        Command::Sessions {
+            project,
+            limit,
+            format,
+        } => {
+            let sessions = match project {
+                Some(project) => {
+                    let project = pomodoro::get_project(project_tracker_data, &project)?;
+                    pomodoro::get_project_sessions(
+                        project_tracker_data,
+                        project.name_getter(),
+                        limit,
+                    )?
+                }
+                None => pomodoro::get_recent_sessions(project_tracker_data, limit)?,
+            };
+            if format != Format::Text {
+                println!(
+                    "{}",
+                    output::render(format, &output::sessions_document(&sessions))
+                );
+                return Ok(());
+            }
+            for session in sessions.iter() {
+                let started = project_tracker_data
+                    .format_local_time(session.entry.started_at)
+                    .map_err(|e| format!("Error formatting time: {}", e))?;
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}{}",
+                    session.id,
+                    started,
+                    session.project_name,
+                    session.entry.phase.as_str(),
+                    cli::format_duration(Duration::from_secs(session.entry.elapsed_seconds)),
+                    session.entry.outcome.as_str(),
+                    cli::format_adjustment(session.entry.adjustment_seconds)
+                );
+            }
+            Ok(())
+        }
+        Command::Journal { project } => {
+            pomodoro::write_journal(project_tracker_data, &project)?;
+            Ok(())
+        }
+        Command::Rename { project, new_name } => {
+            pomodoro::rename_project(project_tracker_data, &project, &new_name)?;
+            println!("Project renamed! ✅");
+            Ok(())
+        }
+        Command::Delete { project, yes } => {
+            if !yes && !cli::confirm(&format!("Archive '{}'?", project)) {
+                return Err("Archiving cancelled! 🚫".to_string());
+            }
+            pomodoro::archive_project(project_tracker_data, &project)?;
+            println!("Project archived! ✅");
+            Ok(())
+        }
+        Command::Restore { project } => {
+            pomodoro::restore_project(project_tracker_data, &project)?;
+            println!("Project restored! ✅");
+            Ok(())
+        }
+        Command::Purge { project, yes } => {
+            if !yes
+                && !cli::confirm(&format!(
+                    "Permanently purge '{}' with its time, history and journal?",
+                    project
+                ))
+            {
+                return Err("Purge cancelled! 🚫".to_string());
+            }
+            pomodoro::purge_project(project_tracker_data, &project)?;
+            println!("Project purged for good! ✅");
+            Ok(())
+        }
+        Command::Log {
+            project,
+            start,
+            minutes,
+            end,
+        } => {
+            let started_at = parse_time(project_tracker_data, &start)?;
+            let ended_at = match (minutes, end) {
+                // log_manual_session turns away anything over a day
+                (Some(minutes), _) => started_at
+                    .checked_add((minutes * 60.0).round() as i64)
+                    .ok_or("The end of that session is out of range.")?,
+                (None, Some(end)) => parse_time(project_tracker_data, &end)?,
+                (None, None) => unreachable!(),
+            };
+            pomodoro::log_manual_session(project_tracker_data, &project, started_at, ended_at)?;
+            println!("Session logged! ✅");
+            Ok(())
+        }
+        Command::Undo { yes } => {
+            if cli::undo_last_log(project_tracker_data, yes)? {
+                println!("Undone! ✅ A copy is kept in the audit log.");
+            }
+            Ok(())
+        }
+        Command::Presets => {
+            let default_name = pomodoro::get_setting(project_tracker_data, "default_preset")?;
+            for preset in pomodoro::get_presets(project_tracker_data)? {
+                let marker = if default_name.as_deref() == Some(preset.name.as_str()) {
+                    " (default)"
+                } else {
+                    ""
+                };
+                println!("{}{}", cli::describe_preset(&preset), marker);
+            }
+            Ok(())
+        }
+        Command::Hooks => {
+            println!(
+                "Hooks live in {}, named after their event:",
+                hooks::hooks_dir(project_tracker_data).display()
+            );
+            for hook in Hook::ALL {
+                let marker = if hooks::hook_path(project_tracker_data, hook).is_some() {
+                    " (installed)"
+                } else {
+                    ""
+                };
+                println!("{}{}", hook.name(), marker);
+            }
+            Ok(())
+        }
+        Command::Config { key, value, unset } => match (key, value) {
+            (None, _) => {
+                for setting in config::SETTINGS {
+                    let value = config::get(project_tracker_data, setting.key)?;
+                    println!(
+                        "{} = {}\t# {}",
+                        setting.key,
+                        value.as_deref().unwrap_or("(default)"),
+                        setting.description
+                    );
+                }
+                Ok(())
+            }
+            (Some(key), _) if unset => {
+                config::unset(project_tracker_data, &key)?;
+                println!("'{}' is back to its default. ✅", key);
+                Ok(())
+            }
+            (Some(key), Some(value)) => {
+                config::set(project_tracker_data, &key, &value)?;
+                println!("Setting saved! ✅");
+                Ok(())
+            }
+            (Some(key), None) => {
+                match config::get(project_tracker_data, &key)? {
+                    Some(value) => println!("{}", value),
+                    None => println!("(default)"),
+                }
+                Ok(())
+            }
+        },
+        #[cfg(unix)]
+        Command::Daemon => crate::daemon::run(project_tracker_data),
+        #[cfg(unix)]
+        Command::Ctl(control) => {
+            let reply = crate::daemon::request(project_tracker_data, &control)?;
+            println!("{}", reply.message);
+            Ok(())
+        }
+        #[cfg(not(unix))]
+        Command::Daemon | Command::Ctl(_) => {
+            Err("The daemon needs a Unix domain socket, which this system lacks.".to_string())
+        }
+    }
+}
+
+fn to_seconds(minutes: Option<f32>) -> Option<u64> {
+    minutes.map(|minutes| (minutes * 60.0).round() as u64)
+}
+
+fn parse_time(project_tracker_data: &ProjectTrackerDb, text: &str) -> Result<i64, String> {
+    match project_tracker_data.parse_local_time(text) {
+        Ok(Some(timestamp)) => Ok(timestamp),
+        Ok(None) => Err(format!(
+            "Can't read the time '{}'. Use 'YYYY-MM-DD HH:MM' or 'HH:MM'.",
+            text
+        )),
+        Err(e) => Err(format!("Error reading time: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args;
+
+    fn log(project_tracker_data: &ProjectTrackerDb, start: &str, end: &str) -> Result<(), String> {
+        let invocation = args::parse(
+            ["log", "Writing", "--start", start, "--end", end]
+                .into_iter()
+                .map(str::to_string),
+        )?;
+        run(invocation.command, project_tracker_data)
+    }
+
+    #[test]
+    fn log_holds_a_session_to_a_day() {
+        let dir = std::env::temp_dir().join(format!("pomo-log-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let project_tracker_data = ProjectTrackerDb::new(&dir).unwrap();
+        pomodoro::create_project(&project_tracker_data, "Writing").unwrap();
+
+        let error = log(
+            &project_tracker_data,
+            "2020-01-01 09:00",
+            "2024-01-01 09:00",
+        )
+        .unwrap_err();
+        assert_eq!(error, "A session can't be longer than 24 hours.");
+        let error = log(
+            &project_tracker_data,
+            "2020-01-01 09:00",
+            "2020-01-02 09:01",
+        )
+        .unwrap_err();
+        assert_eq!(error, "A session can't be longer than 24 hours.");
+        log(
+            &project_tracker_data,
+            "2020-01-01 09:00",
+            "2020-01-02 09:00",
+        )
+        .unwrap();
+
+        let writing = pomodoro::get_project(&project_tracker_data, "Writing").unwrap();
+        assert_eq!(writing.time_getter(), Duration::from_secs(24 * 60 * 60));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+// user preferences, stored in the settings table and managed with `pomo config`
+use crate::pomodoro;
+use crate::session::BreakRule;
+use crate::statusline;
+use crate::terminal;
+use crate::tracker::{Preset, ProjectTrackerDb, TimerSettings};
+
+pub struct Setting {
+    pub key: &'static str,
+    pub description: &'static str,
+    // checks a value and returns it in the form it is stored in
+    normalize: fn(&ProjectTrackerDb, &str) -> Result<String, String>,
+}
+
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "default_preset",
+        description: "Preset used when none is picked (default: classic 25/5/15)",
+        normalize: preset_name,
+    },
+    Setting {
+        key: "long_break_interval",
+        description: "Pomodoros per long rest (default 4)",
+        normalize: positive_integer,
+    },
+    Setting {
+        key: "cycles",
+        description: "Cycles after which a session ends on its own (default: until you stop it)",
+        normalize: positive_integer,
+    },
+    Setting {
+        key: "count_skipped_rests",
+        description: "Whether skipped rests count toward the long rest (default false)",
+        normalize: boolean,
+    },
+    Setting {
+        key: "flow_break_ratio",
+        description: "Flowtime break as a fraction of the focus time (default 0.2)",
+        normalize: ratio,
+    },
+    Setting {
+        key: "flow_break_table",
+        description: "Flowtime breaks by focus length in minutes, e.g. 25:5,50:8,90:15 (wins over the ratio)",
+        normalize: break_table,
+    },
+    Setting {
+        key: "desktop_notifications",
+        description: "Whether phase changes pop up desktop notifications over D-Bus (default true)",
+        normalize: boolean,
+    },
+    Setting {
+        key: "terminal_title",
+        description: "Whether the terminal title shows the running timer (default true)",
+        normalize: boolean,
+    },
+    Setting {
+        key: "terminal_notifications",
+        description: "Notify through the terminal when a timer runs out: auto, off, osc9, osc777 or osc99 (default auto)",
+        normalize: terminal_protocol,
+    },
+    Setting {
+        key: "hook_timeout",
+        description: "Seconds a hook may run before it is stopped (default 10)",
+        normalize: positive_integer,
+    },
+    Setting {
+        key: "status_line_format",
+        description: "Template of 'pomo status --line', e.g. {phase_icon} {project} {remaining:mm:ss}",
+        normalize: status_template,
+    },
+];
+
+fn find(key: &str) -> Result<&'static Setting, String> {
+    SETTINGS
+        .iter()
+        .find(|setting| setting.key == key)
+        .ok_or(format!(
+            "Unknown setting '{}'. Run 'pomo config' to list them.",
+            key
+        ))
+}
+
+pub fn get(project_tracker_data: &ProjectTrackerDb, key: &str) -> Result<Option<String>, String> {
+    find(key)?;
+    pomodoro::get_setting(project_tracker_data, key)
+}
+
+pub fn set(project_tracker_data: &ProjectTrackerDb, key: &str, value: &str) -> Result<(), String> {
+    let setting = find(key)?;
+    let value = (setting.normalize)(project_tracker_data, value)
+        .map_err(|e| format!("Invalid value for '{}': {}", key, e))?;
+    pomodoro::set_setting(project_tracker_data, key, &value)
+}
+
+pub fn unset(project_tracker_data: &ProjectTrackerDb, key: &str) -> Result<bool, String> {
+    find(key)?;
+    pomodoro::unset_setting(project_tracker_data, key)
+}
+
+// the built-in defaults with the user's settings applied
+pub fn timer_settings(project_tracker_data: &ProjectTrackerDb) -> Result<TimerSettings, String> {
+    let mut settings = TimerSettings::default();
+    if let Some(name) = get(project_tracker_data, "default_preset")? {
+        // a preset deleted behind our back just means the built-in lengths
+        if let Ok(preset) = pomodoro::get_preset(project_tracker_data, &name) {
+            apply_preset(&mut settings, &preset);
+        }
+    }
+    if let Some(value) = get(project_tracker_data, "long_break_interval")? {
+        settings.long_break_interval = value.parse().unwrap_or(settings.long_break_interval);
+    }
+    if let Some(value) = get(project_tracker_data, "cycles")? {
+        settings.target_cycles = value.parse().ok();
+    }
+    if let Some(value) = get(project_tracker_data, "count_skipped_rests")? {
+        settings.count_skipped_rests = value == "true";
+    }
+    Ok(settings)
+}
+
+// timer_settings with the project's own rhythm on top, if it has one
+pub fn project_timer_settings(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+) -> Result<TimerSettings, String> {
+    let mut settings = timer_settings(project_tracker_data)?;
+    if let Some(defaults) =
+        pomodoro::get_project_timer_defaults(project_tracker_data, project_name)?
+    {
+        defaults.apply(&mut settings);
+    }
+    Ok(settings)
+}
+
+// how Flowtime breaks are worked out: the table if there is one, else the ratio
+pub fn break_rule(project_tracker_data: &ProjectTrackerDb) -> Result<BreakRule, String> {
+    if let Some(table) = get(project_tracker_data, "flow_break_table")?
+        && let Ok(rows) = parse_break_table(&table)
+    {
+        return Ok(BreakRule::Table(rows));
+    }
+    match get(project_tracker_data, "flow_break_ratio")? {
+        Some(value) => Ok(value.parse().map(BreakRule::Ratio).unwrap_or_default()),
+        None => Ok(BreakRule::default()),
+    }
+}
+
+pub fn apply_preset(settings: &mut TimerSettings, preset: &Preset) {
+    settings.focus_seconds = preset.focus_seconds;
+    settings.rest_seconds = preset.rest_seconds;
+    settings.long_rest_seconds = preset.long_rest_seconds;
+}
+
+fn preset_name(project_tracker_data: &ProjectTrackerDb, value: &str) -> Result<String, String> {
+    pomodoro::get_preset(project_tracker_data, value).map(|preset| preset.name)
+}
+
+fn ratio(_: &ProjectTrackerDb, value: &str) -> Result<String, String> {
+    match value.trim().parse::<f64>() {
+        Ok(ratio) if ratio > 0.0 && ratio.is_finite() => Ok(ratio.to_string()),
+        _ => Err(format!(
+            "expected a number above 0, like 0.2, got '{}'",
+            value
+        )),
+    }
+}
+
+fn break_table(_: &ProjectTrackerDb, value: &str) -> Result<String, String> {
+    let rows = parse_break_table(value)?;
+    let rows: Vec<String> = rows
+        .iter()
+        .map(|(up_to, rest)| format!("{}:{}", up_to / 60, rest / 60))
+        .collect();
+    Ok(rows.join(","))
+}
+
+// "25:5,50:8" -> [(1500, 300), (3000, 480)], sorted by focus length
+fn parse_break_table(value: &str) -> Result<Vec<(u64, u64)>, String> {
+    let mut rows = Vec::new();
+    for row in value.split(',') {
+        let parsed = row.split_once(':').and_then(|(up_to, rest)| {
+            Some((
+                up_to.trim().parse::<u64>().ok()?,
+                rest.trim().parse::<u64>().ok()?,
+            ))
+        });
+        match parsed {
+            Some((up_to, rest)) if up_to > 0 && rest > 0 => rows.push((up_to * 60, rest * 60)),
+            _ => {
+                return Err(format!(
+                    "expected rows of '<focus minutes>:<break minutes>', got '{}'",
+                    row.trim()
+                ));
+            }
+        }
+    }
+    rows.sort();
+    Ok(rows)
+}
+
+fn status_template(_: &ProjectTrackerDb, value: &str) -> Result<String, String> {
+    statusline::check_template(value)?;
+    Ok(value.to_string())
+}
+
+fn terminal_protocol(_: &ProjectTrackerDb, value: &str) -> Result<String, String> {
+    let value = value.trim().to_ascii_lowercase();
+    if value == "auto" || value == "off" || terminal::Protocol::parse(&value).is_some() {
+        Ok(value)
+    } else {
+        Err(format!(
+            "expected auto, off, osc9, osc777 or osc99, got '{}'",
+            value
+        ))
+    }
+}
+
+fn positive_integer(_: &ProjectTrackerDb, value: &str) -> Result<String, String> {
+    match value.trim().parse::<u64>() {
+        Ok(number) if number > 0 => Ok(number.to_string()),
+        _ => Err(format!("expected a whole number above 0, got '{}'", value)),
+    }
+}
+
+fn boolean(_: &ProjectTrackerDb, value: &str) -> Result<String, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok("true".to_string()),
+        "false" | "no" | "off" | "0" => Ok("false".to_string()),
+        _ => Err(format!("expected true or false, got '{}'", value)),
+    }
+}
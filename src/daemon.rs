@@ -0,0 +1,465 @@
+// the timer as a background process: `pomo daemon` owns a Session and takes
+// requests on a Unix domain socket in the data directory; `pomo ctl` sends them.
+//
+// the protocol is one request per connection, as a single line of
+// tab-separated fields (names never contain tabs), answered with
+// "ok\t<message>" or "error\t<message>" and then zero or more
+// "<field>\t<value>" lines
+use crate::args::Control;
+use crate::cli::{self, CHECKPOINT_INTERVAL, format_duration};
+use crate::config;
+use crate::hooks;
+use crate::notify::Notifications;
+use crate::paths;
+use crate::pomodoro;
+use crate::session::{Event, Input, Session, SystemClock};
+use crate::tracker::{Phase, ProjectTrackerDb, SessionOutcome};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// a client (or daemon) that says nothing for this long is given up on
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+// how long the loop sleeps between timer updates
+const TICK: Duration = Duration::from_millis(100);
+
+// the daemon's answer to one request
+pub struct Reply {
+    pub message: String,
+    pub fields: Vec<(String, String)>,
+}
+impl Reply {
+    fn new(message: String) -> Self {
+        Reply {
+            message,
+            fields: Vec::new(),
+        }
+    }
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Daemon<'a> {
+    project_tracker_data: &'a ProjectTrackerDb,
+    session: Option<Session<SystemClock>>,
+    // the focus recorded by the last session, once it is over
+    ended_focus_seconds: u64,
+    last_checkpoint: Instant,
+    notifications: Notifications,
+}
+
+// serves requests until the process is killed
+pub fn run(project_tracker_data: &ProjectTrackerDb) -> Result<(), String> {
+    let path = paths::socket_path(project_tracker_data.data_dir_getter());
+    let listener = bind(&path)?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to set up the control socket: {}", e))?;
+    let mut daemon = Daemon {
+        project_tracker_data,
+        session: None,
+        ended_focus_seconds: 0,
+        last_checkpoint: Instant::now(),
+        notifications: Notifications::new(project_tracker_data),
+    };
+    daemon.recover()?;
+    println!("pomo daemon listening on {}", path.display());
+
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = daemon.serve(stream) {
+                    eprintln!("Failed to answer a request: {}", e);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => eprintln!("Failed to accept a connection: {}", e),
+        }
+        let input = daemon.notification_input();
+        if let Err(e) = daemon.tick(input) {
+            eprintln!("{}", e);
+        }
+        thread::sleep(TICK);
+    }
+}
+
+// a socket file nobody answers on was left by a daemon that died
+fn bind(path: &Path) -> Result<UnixListener, String> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!(
+                "A pomo daemon is already listening on {}.",
+                path.display()
+            ));
+        }
+        std::fs::remove_file(path).map_err(|e| {
+            format!(
+                "Failed to remove the stale socket '{}': {}",
+                path.display(),
+                e
+            )
+        })?;
+    }
+    UnixListener::bind(path).map_err(|e| format!("Failed to listen on '{}': {}", path.display(), e))
+}
+
+// sends one request to the daemon of this data directory
+pub fn request(
+    project_tracker_data: &ProjectTrackerDb,
+    control: &Control,
+) -> Result<Reply, String> {
+    let path = paths::socket_path(project_tracker_data.data_dir_getter());
+    let mut stream = UnixStream::connect(&path)
+        .map_err(|_| "No pomo daemon is running. Start one with 'pomo daemon'.".to_string())?;
+    let io_error = |e: io::Error| format!("Failed to talk to the daemon: {}", e);
+    // a daemon stuck on something else must not hang a status bar
+    stream
+        .set_read_timeout(Some(CLIENT_TIMEOUT))
+        .map_err(io_error)?;
+    writeln!(stream, "{}", encode(control)).map_err(io_error)?;
+    let mut lines = BufReader::new(stream).lines();
+    let status = lines
+        .next()
+        .ok_or("The daemon closed the connection without answering.")?
+        .map_err(io_error)?;
+    let (ok, message) = match status.split_once('\t') {
+        Some(("ok", message)) => (true, message),
+        Some(("error", message)) => (false, message),
+        _ => return Err(format!("Unexpected answer from the daemon: '{}'", status)),
+    };
+    if !ok {
+        return Err(message.to_string());
+    }
+    let mut reply = Reply::new(message.to_string());
+    for line in lines {
+        let line = line.map_err(io_error)?;
+        if let Some((field, value)) = line.split_once('\t') {
+            reply.fields.push((field.to_string(), value.to_string()));
+        }
+    }
+    Ok(reply)
+}
+
+fn encode(control: &Control) -> String {
+    match control {
+        Control::Start {
+            project,
+            preset,
+            flow,
+        } => format!(
+            "start\t{}\t{}\t{}",
+            project,
+            preset.as_deref().unwrap_or(""),
+            flow
+        ),
+        Control::Pause => "pause".to_string(),
+        Control::Resume => "resume".to_string(),
+        Control::Skip => "skip".to_string(),
+        Control::Stop => "stop".to_string(),
+        Control::Status => "status".to_string(),
+        Control::Switch { project } => format!("switch\t{}", project),
+    }
+}
+
+fn decode(line: &str) -> Result<Control, String> {
+    let fields: Vec<&str> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+    let control = match fields.as_slice() {
+        ["start", project, preset, flow] => Control::Start {
+            project: project.to_string(),
+            preset: Some(preset.to_string()).filter(|preset| !preset.is_empty()),
+            flow: *flow == "true",
+        },
+        ["pause"] => Control::Pause,
+        ["resume"] => Control::Resume,
+        ["skip"] => Control::Skip,
+        ["stop"] => Control::Stop,
+        ["status"] => Control::Status,
+        ["switch", project] => Control::Switch {
+            project: project.to_string(),
+        },
+        _ => return Err(format!("Unknown request '{}'.", line.trim())),
+    };
+    Ok(control)
+}
+
+impl Daemon<'_> {
+    // a timer left behind by a pomo that is gone is picked up where it stopped
+    fn recover(&mut self) -> Result<(), String> {
+        cli::ensure_no_running_timer(self.project_tracker_data)?;
+        let Some(active) = pomodoro::get_active_session(self.project_tracker_data)? else {
+            return Ok(());
+        };
+        println!(
+            "Resuming the unfinished {} on '{}'{}.",
+            active.phase.as_str(),
+            active.project_name,
+            if active.paused { ", paused" } else { "" }
+        );
+        let session = Session::resume(SystemClock, active)
+            .with_break_rule(config::break_rule(self.project_tracker_data)?);
+        self.session = Some(session);
+        self.tick(None)
+    }
+
+    fn serve(&mut self, stream: UnixStream) -> Result<(), String> {
+        let io_error = |e: io::Error| e.to_string();
+        stream.set_nonblocking(false).map_err(io_error)?;
+        stream
+            .set_read_timeout(Some(CLIENT_TIMEOUT))
+            .map_err(io_error)?;
+        let mut line = String::new();
+        let read = BufReader::new(&stream)
+            .read_line(&mut line)
+            .map_err(io_error)?;
+        // a daemon starting up checks for us by connecting and hanging up
+        if read == 0 {
+            return Ok(());
+        }
+        let answer = match decode(&line).and_then(|control| self.handle(control)) {
+            Ok(reply) => {
+                let mut answer = format!("ok\t{}\n", reply.message);
+                for (field, value) in reply.fields {
+                    answer.push_str(&format!("{}\t{}\n", field, value));
+                }
+                answer
+            }
+            Err(e) => format!("error\t{}\n", e),
+        };
+        (&stream).write_all(answer.as_bytes()).map_err(io_error)
+    }
+
+    fn handle(&mut self, control: Control) -> Result<Reply, String> {
+        match control {
+            Control::Start {
+                project,
+                preset,
+                flow,
+            } => self.start(&project, preset.as_deref(), flow),
+            Control::Pause | Control::Resume => {
+                let pause = control == Control::Pause;
+                let session = self.running()?;
+                if session.is_paused() == pause {
+                    let state = if pause { "paused" } else { "running" };
+                    return Err(format!("The timer is already {}.", state));
+                }
+                self.tick(Some(Input::TogglePause))?;
+                Ok(Reply::new(
+                    if pause { "Paused." } else { "Resumed." }.to_string(),
+                ))
+            }
+            Control::Skip => {
+                self.running()?;
+                self.tick(Some(Input::Skip))?;
+                match self.session.as_ref() {
+                    Some(session) => Ok(Reply::new(format!(
+                        "Skipped to {}.",
+                        phase_label(session.phase()).to_lowercase()
+                    ))),
+                    None => Ok(Reply::new("Skipped; the session is over.".to_string())),
+                }
+            }
+            Control::Stop => {
+                self.running()?;
+                self.tick(Some(Input::Quit))?;
+                Ok(Reply::new(format!(
+                    "Stopped. Total focus recorded: {}",
+                    format_duration(Duration::from_secs(self.ended_focus_seconds))
+                )))
+            }
+            Control::Status => Ok(self.status()),
+            Control::Switch { project } => {
+                self.running()?;
+                let project = pomodoro::get_project(self.project_tracker_data, &project)?;
+                let name = project.name_getter().to_string();
+                if let Some(session) = self.session.as_mut() {
+                    session.switch_project(&name);
+                }
+                self.checkpoint()?;
+                println!("Switched to '{}'.", name);
+                Ok(Reply::new(format!("Now recording on '{}'.", name)))
+            }
+        }
+    }
+
+    fn start(&mut self, project: &str, preset: Option<&str>, flow: bool) -> Result<Reply, String> {
+        if let Some(session) = self.session.as_ref() {
+            return Err(format!(
+                "A timer is already running on '{}'. Stop it first.",
+                session.project_name()
+            ));
+        }
+        // a timer in an interactive pomo shows up as a fresh checkpoint
+        cli::ensure_no_running_timer(self.project_tracker_data)?;
+        let project = pomodoro::get_project(self.project_tracker_data, project)?;
+        let mut settings =
+            config::project_timer_settings(self.project_tracker_data, project.name_getter())?;
+        if let Some(preset) = preset {
+            let preset = pomodoro::get_preset(self.project_tracker_data, preset)?;
+            config::apply_preset(&mut settings, &preset);
+        }
+        settings.flow = flow;
+        let session = Session::new(SystemClock, project.name_getter(), &settings)
+            .with_break_rule(config::break_rule(self.project_tracker_data)?);
+        hooks::session_started(self.project_tracker_data, project.name_getter(), &settings);
+        self.session = Some(session);
+        self.tick(None)?;
+        Ok(Reply::new(format!(
+            "Started focus on '{}'.",
+            project.name_getter()
+        )))
+    }
+
+    fn running(&self) -> Result<&Session<SystemClock>, String> {
+        self.session
+            .as_ref()
+            .ok_or("No timer is running. Start one with 'pomo ctl start <project>'.".to_string())
+    }
+
+    fn status(&self) -> Reply {
+        let Some(session) = self.session.as_ref() else {
+            let mut reply = Reply::new("Idle.".to_string());
+            reply.fields.push(("state".to_string(), "idle".to_string()));
+            return reply;
+        };
+        let shown = if session.is_counting_up() {
+            format!("{} elapsed", format_duration(session.elapsed()))
+        } else {
+            format!("{} remaining", format_duration(session.remaining()))
+        };
+        let state = if session.is_paused() {
+            "paused"
+        } else {
+            "running"
+        };
+        let mut reply = Reply::new(format!(
+            "{} on '{}', cycle {}: {}{}",
+            phase_label(session.phase()),
+            session.project_name(),
+            session.cycle(),
+            shown,
+            if session.is_paused() { " (paused)" } else { "" }
+        ));
+        let remaining = session.remaining().as_millis().div_ceil(1000);
+        for (field, value) in [
+            ("state", state.to_string()),
+            ("project", session.project_name().to_string()),
+            ("phase", session.phase().as_str().to_string()),
+            ("cycle", session.cycle().to_string()),
+            ("remaining_seconds", remaining.to_string()),
+            ("elapsed_seconds", session.elapsed().as_secs().to_string()),
+            ("planned_seconds", session.planned_seconds().to_string()),
+            ("flow", session.is_flow().to_string()),
+        ] {
+            reply.fields.push((field.to_string(), value));
+        }
+        reply
+    }
+
+    // a button pressed on one of the daemon's desktop notifications
+    fn notification_input(&mut self) -> Option<Input> {
+        let session = self.session.as_ref()?;
+        self.notifications.take_input(session)
+    }
+
+    // advances the timer and acts on what happened: records finished timers
+    // and keeps the checkpoint current
+    fn tick(&mut self, input: Option<Input>) -> Result<(), String> {
+        let Some(session) = self.session.as_mut() else {
+            return Ok(());
+        };
+        let mut input = input;
+        let mut checkpoint = false;
+        loop {
+            for event in session.update(input.take())? {
+                hooks::session_event(self.project_tracker_data, session, &event);
+                self.notifications.on_event(session, &event);
+                match event {
+                    Event::PhaseStarted { phase, cycle } => {
+                        println!(
+                            "{} started on '{}' (cycle {}).",
+                            phase_label(phase),
+                            session.project_name(),
+                            cycle
+                        );
+                        checkpoint = true;
+                    }
+                    Event::Paused | Event::Resumed | Event::Adjusted { .. } => checkpoint = true,
+                    // nobody is at the terminal to ask, so the gap is left out
+                    Event::ClockJumped { gap } => {
+                        println!(
+                            "The clock jumped ahead by {}; that time is not counted.",
+                            format_duration(gap)
+                        );
+                        input = Some(Input::CountSuspended(false));
+                    }
+                    Event::PhaseEnded(entry) => {
+                        if entry.elapsed_seconds > 0 {
+                            pomodoro::record_session(
+                                self.project_tracker_data,
+                                session.project_name(),
+                                &entry,
+                            )?;
+                        }
+                        pomodoro::clear_active_session(self.project_tracker_data)?;
+                        let outcome = match entry.outcome {
+                            SessionOutcome::Completed => "completed",
+                            SessionOutcome::Skipped => "skipped",
+                            SessionOutcome::Quit => "stopped",
+                        };
+                        println!(
+                            "{} {} after {}.",
+                            phase_label(entry.phase),
+                            outcome,
+                            format_duration(Duration::from_secs(entry.elapsed_seconds))
+                        );
+                        if entry.outcome == SessionOutcome::Completed
+                            && !self.notifications.is_available()
+                        {
+                            print!("\x07");
+                            let _ = io::stdout().flush();
+                        }
+                    }
+                    Event::Ended { target_reached } => {
+                        if target_reached {
+                            println!("All cycles done!");
+                        }
+                    }
+                }
+            }
+            if input.is_none() {
+                break;
+            }
+        }
+        if session.is_ended() {
+            self.ended_focus_seconds = session.focus_seconds();
+            self.session = None;
+            return Ok(());
+        }
+        if checkpoint || self.last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+            self.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) -> Result<(), String> {
+        if let Some(session) = self.session.as_ref() {
+            pomodoro::save_active_session(self.project_tracker_data, &session.snapshot())?;
+        }
+        self.last_checkpoint = Instant::now();
+        Ok(())
+    }
+}
+
+fn phase_label(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Focus => "Focus",
+        Phase::Rest => "Rest",
+        Phase::LongRest => "Long rest",
+    }
+}
@@ -0,0 +1,622 @@
+// just enough of the D-Bus wire protocol to call methods on the session bus
+// and hear the signals that come back, without linking libdbus: messages are
+// written little-endian, and replies of basic types (bytes, integers,
+// strings) are read in either byte order
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const METHOD_CALL: u8 = 1;
+pub const METHOD_RETURN: u8 = 2;
+pub const ERROR: u8 = 3;
+pub const SIGNAL: u8 = 4;
+
+// header field codes
+const PATH: u8 = 1;
+const INTERFACE: u8 = 2;
+const MEMBER: u8 = 3;
+const ERROR_NAME: u8 = 4;
+const REPLY_SERIAL: u8 = 5;
+const DESTINATION: u8 = 6;
+const SIGNATURE: u8 = 8;
+
+// messages bigger than this are not ours to read
+const MAX_MESSAGE: usize = 1 << 20;
+// how long the bus may take to sign us in, and to take a message from us
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Byte(u8),
+    U32(u32),
+    I32(i32),
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+    StrArray(Vec<String>),
+    // a{sv}, as used for hints and properties
+    Dict(Vec<(String, Arg)>),
+}
+impl Arg {
+    fn signature(&self) -> &'static str {
+        match self {
+            Arg::Byte(_) => "y",
+            Arg::U32(_) => "u",
+            Arg::I32(_) => "i",
+            Arg::Str(_) => "s",
+            Arg::ObjectPath(_) => "o",
+            Arg::Signature(_) => "g",
+            Arg::StrArray(_) => "as",
+            Arg::Dict(_) => "a{sv}",
+        }
+    }
+}
+
+// a message read off the bus; `body` is empty unless it holds only basic types
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub kind: u8,
+    pub serial: u32,
+    pub reply_serial: Option<u32>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub body: Vec<Arg>,
+}
+
+pub struct Connection {
+    stream: UnixStream,
+    serial: u32,
+}
+
+impl Connection {
+    // connects to the session bus named by DBUS_SESSION_BUS_ADDRESS (or the
+    // usual $XDG_RUNTIME_DIR/bus), signs in and says Hello
+    pub fn session() -> io::Result<Self> {
+        Self::open(connect_session_bus()?)
+    }
+
+    // signs in on a fresh connection to a bus and says Hello; a bus that
+    // doesn't answer in time is given up on, so it can't hang the timer
+    fn open(stream: UnixStream) -> io::Result<Self> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut connection = Connection { stream, serial: 0 };
+        connection.authenticate()?;
+        let hello = connection.call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+            &[],
+        )?;
+        loop {
+            let message = connection.read_message()?;
+            if message.reply_serial == Some(hello) {
+                if message.kind != METHOD_RETURN {
+                    return Err(io::Error::other("the bus refused our Hello"));
+                }
+                // replies and signals come whenever they come from here on
+                connection.stream.set_read_timeout(None)?;
+                return Ok(connection);
+            }
+        }
+    }
+
+    // a second handle on the same connection, e.g. for a reading thread
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Connection {
+            stream: self.stream.try_clone()?,
+            serial: self.serial,
+        })
+    }
+
+    // ends the connection for every handle on it, waking a blocked reader
+    pub fn shutdown(&self) {
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    // SASL EXTERNAL: the bus checks our uid against the socket's credentials
+    fn authenticate(&mut self) -> io::Result<()> {
+        // SAFETY: getuid has no preconditions and cannot fail
+        let uid = unsafe { libc::getuid() };
+        let hex_uid: String = uid
+            .to_string()
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.stream.write_all(b"\0")?;
+        self.stream
+            .write_all(format!("AUTH EXTERNAL {}\r\n", hex_uid).as_bytes())?;
+        let answer = self.read_auth_line()?;
+        if !answer.starts_with("OK ") {
+            return Err(io::Error::other(format!(
+                "the bus refused to authenticate us: {}",
+                answer
+            )));
+        }
+        self.stream.write_all(b"BEGIN\r\n")
+    }
+
+    // byte by byte, so nothing after the line is swallowed
+    fn read_auth_line(&mut self) -> io::Result<String> {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while !line.ends_with(b"\r\n") {
+            self.stream.read_exact(&mut byte)?;
+            line.push(byte[0]);
+            if line.len() > 512 {
+                return Err(io::Error::other("the bus sent an overlong auth line"));
+            }
+        }
+        line.truncate(line.len() - 2);
+        Ok(String::from_utf8_lossy(&line).to_string())
+    }
+
+    // the serial the next call goes out with, for a reader that must know
+    // its reply even if it comes back before call() returns
+    pub fn next_serial(&self) -> u32 {
+        self.serial + 1
+    }
+
+    // sends a method call without waiting for its reply; returns its serial
+    pub fn call(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        args: &[Arg],
+    ) -> io::Result<u32> {
+        self.serial += 1;
+        let message = encode_call(self.serial, destination, path, interface, member, args);
+        self.stream.write_all(&message)?;
+        Ok(self.serial)
+    }
+
+    // blocks until the next message arrives
+    pub fn read_message(&mut self) -> io::Result<Message> {
+        let mut fixed = [0u8; 16];
+        self.stream.read_exact(&mut fixed)?;
+        let little = match fixed[0] {
+            b'l' => true,
+            b'B' => false,
+            _ => return Err(io::Error::other("the bus sent a message we can't read")),
+        };
+        let number = |bytes: &[u8]| {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            if little {
+                u32::from_le_bytes(bytes)
+            } else {
+                u32::from_be_bytes(bytes)
+            }
+        };
+        let body_length = number(&fixed[4..8]) as usize;
+        let fields_length = number(&fixed[12..16]) as usize;
+        let header_length = (16 + fields_length).next_multiple_of(8);
+        if header_length + body_length > MAX_MESSAGE {
+            return Err(io::Error::other("the bus sent an oversized message"));
+        }
+        let mut rest = vec![0u8; header_length - 16 + body_length];
+        self.stream.read_exact(&mut rest)?;
+        let mut data = fixed.to_vec();
+        data.extend(rest);
+
+        let mut message = Message {
+            kind: fixed[1],
+            serial: number(&fixed[8..12]),
+            reply_serial: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            body: Vec::new(),
+        };
+        let mut signature = String::new();
+        let mut reader = Reader {
+            data: &data[..16 + fields_length],
+            pos: 16,
+            little,
+        };
+        while reader.pos < reader.data.len() {
+            reader.align(8);
+            let code = reader.byte()?;
+            let value_signature = reader.signature()?;
+            let value = reader.basic(&value_signature)?;
+            match (code, value) {
+                (INTERFACE, Arg::Str(s)) => message.interface = Some(s),
+                (MEMBER, Arg::Str(s)) => message.member = Some(s),
+                (ERROR_NAME, Arg::Str(s)) => message.error_name = Some(s),
+                (REPLY_SERIAL, Arg::U32(n)) => message.reply_serial = Some(n),
+                (SIGNATURE, Arg::Signature(s)) => signature = s,
+                _ => {}
+            }
+        }
+        let mut body = Reader {
+            data: &data[header_length..],
+            pos: 0,
+            little,
+        };
+        let mut values = Vec::new();
+        for code in signature.chars() {
+            match body.basic(&code.to_string()) {
+                Ok(value) => values.push(value),
+                // a container we don't read; leave the body empty
+                Err(_) => {
+                    values.clear();
+                    break;
+                }
+            }
+        }
+        message.body = values;
+        Ok(message)
+    }
+}
+
+fn connect_session_bus() -> io::Result<UnixStream> {
+    let address = std::env::var("DBUS_SESSION_BUS_ADDRESS").ok();
+    let Some(address) = address.filter(|address| !address.is_empty()) else {
+        // systemd-style sessions keep the bus at a fixed place
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .ok_or_else(|| io::Error::other("no session bus address"))?;
+        return UnixStream::connect(PathBuf::from(runtime_dir).join("bus"));
+    };
+    let mut last_error = io::Error::other("no usable session bus address");
+    // the address may list several, to be tried in turn
+    for entry in address.split(';') {
+        let Some(options) = entry.strip_prefix("unix:") else {
+            continue;
+        };
+        for option in options.split(',') {
+            let attempt = match option.split_once('=') {
+                Some(("path", path)) => UnixStream::connect(unescape(path)),
+                #[cfg(target_os = "linux")]
+                Some(("abstract", name)) => connect_abstract(&unescape(name)),
+                _ => continue,
+            };
+            match attempt {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = e,
+            }
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(target_os = "linux")]
+fn connect_abstract(name: &str) -> io::Result<UnixStream> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::SocketAddr;
+    let address = SocketAddr::from_abstract_name(name.as_bytes())?;
+    UnixStream::connect_addr(&address)
+}
+
+// addresses escape unusual bytes as %xx
+fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn encode_call(
+    serial: u32,
+    destination: &str,
+    path: &str,
+    interface: &str,
+    member: &str,
+    args: &[Arg],
+) -> Vec<u8> {
+    let mut body = Writer::default();
+    for arg in args {
+        body.arg(arg);
+    }
+    let signature: String = args.iter().map(Arg::signature).collect();
+
+    let mut header = Writer::default();
+    header.buf.extend([b'l', METHOD_CALL, 0, 1]);
+    header.u32(body.buf.len() as u32);
+    header.u32(serial);
+    let mut fields = vec![
+        (PATH, Arg::ObjectPath(path.to_string())),
+        (INTERFACE, Arg::Str(interface.to_string())),
+        (MEMBER, Arg::Str(member.to_string())),
+        (DESTINATION, Arg::Str(destination.to_string())),
+    ];
+    if !signature.is_empty() {
+        fields.push((SIGNATURE, Arg::Signature(signature)));
+    }
+    header.array(8, |header| {
+        for (code, value) in fields.iter() {
+            header.align(8);
+            header.buf.push(*code);
+            header.variant(value);
+        }
+    });
+    header.align(8);
+    header.buf.extend(body.buf);
+    header.buf
+}
+
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+impl Writer {
+    fn align(&mut self, alignment: usize) {
+        let padded = self.buf.len().next_multiple_of(alignment);
+        self.buf.resize(padded, 0);
+    }
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend(value.to_le_bytes());
+    }
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buf.extend(value.as_bytes());
+        self.buf.push(0);
+    }
+    fn signature(&mut self, value: &str) {
+        self.buf.push(value.len() as u8);
+        self.buf.extend(value.as_bytes());
+        self.buf.push(0);
+    }
+    // the length is of the elements only, not the padding before the first
+    fn array(&mut self, element_alignment: usize, elements: impl FnOnce(&mut Self)) {
+        self.u32(0);
+        let length_at = self.buf.len() - 4;
+        self.align(element_alignment);
+        let start = self.buf.len();
+        elements(self);
+        let length = (self.buf.len() - start) as u32;
+        self.buf[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+    }
+    fn variant(&mut self, value: &Arg) {
+        self.signature(value.signature());
+        self.arg(value);
+    }
+    fn arg(&mut self, arg: &Arg) {
+        match arg {
+            Arg::Byte(value) => self.buf.push(*value),
+            Arg::U32(value) => self.u32(*value),
+            Arg::I32(value) => {
+                self.align(4);
+                self.buf.extend(value.to_le_bytes());
+            }
+            Arg::Str(value) | Arg::ObjectPath(value) => self.string(value),
+            Arg::Signature(value) => self.signature(value),
+            Arg::StrArray(values) => self.array(4, |writer| {
+                for value in values {
+                    writer.string(value);
+                }
+            }),
+            Arg::Dict(entries) => self.array(8, |writer| {
+                for (key, value) in entries {
+                    writer.align(8);
+                    writer.string(key);
+                    writer.variant(value);
+                }
+            }),
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    little: bool,
+}
+impl Reader<'_> {
+    fn truncated() -> io::Error {
+        io::Error::other("the bus sent a truncated message")
+    }
+    fn align(&mut self, alignment: usize) {
+        self.pos = self.pos.next_multiple_of(alignment);
+    }
+    fn take(&mut self, length: usize) -> io::Result<&[u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + length)
+            .ok_or_else(Self::truncated)?;
+        self.pos += length;
+        Ok(bytes)
+    }
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+    fn u32(&mut self) -> io::Result<u32> {
+        self.align(4);
+        let little = self.little;
+        let bytes = self.take(4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Ok(if little {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.u32()? as usize;
+        let text = String::from_utf8_lossy(self.take(length)?).to_string();
+        self.take(1)?;
+        Ok(text)
+    }
+    fn signature(&mut self) -> io::Result<String> {
+        let length = self.byte()? as usize;
+        let text = String::from_utf8_lossy(self.take(length)?).to_string();
+        self.take(1)?;
+        Ok(text)
+    }
+    // one value of a basic type
+    fn basic(&mut self, signature: &str) -> io::Result<Arg> {
+        match signature {
+            "y" => Ok(Arg::Byte(self.byte()?)),
+            "u" => Ok(Arg::U32(self.u32()?)),
+            "i" => Ok(Arg::I32(self.u32()? as i32)),
+            "s" => Ok(Arg::Str(self.string()?)),
+            "o" => Ok(Arg::Str(self.string()?)),
+            "g" => Ok(Arg::Signature(self.signature()?)),
+            _ => Err(io::Error::other(format!(
+                "can't read D-Bus values of type '{}'",
+                signature
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Instant;
+
+    // what a bus sends back for the call with serial `reply_to`
+    fn encode_reply(serial: u32, reply_to: u32, args: &[Arg]) -> Vec<u8> {
+        let mut body = Writer::default();
+        for arg in args {
+            body.arg(arg);
+        }
+        let signature: String = args.iter().map(Arg::signature).collect();
+        let mut header = Writer::default();
+        header.buf.extend([b'l', METHOD_RETURN, 0, 1]);
+        header.u32(body.buf.len() as u32);
+        header.u32(serial);
+        header.array(8, |header| {
+            header.buf.push(REPLY_SERIAL);
+            header.variant(&Arg::U32(reply_to));
+            header.align(8);
+            header.buf.push(SIGNATURE);
+            header.variant(&Arg::Signature(signature));
+        });
+        header.align(8);
+        header.buf.extend(body.buf);
+        header.buf
+    }
+
+    fn read_auth_line(stream: &mut UnixStream) -> String {
+        let mut peer = Connection {
+            stream: stream.try_clone().unwrap(),
+            serial: 0,
+        };
+        peer.read_auth_line().unwrap()
+    }
+
+    // the bus end of a connection: signs the client in, answers its Hello,
+    // then answers each method call named in `answers` in turn
+    fn fake_bus(mut stream: UnixStream, answers: Vec<(&'static str, Vec<Arg>)>) {
+        let mut nul = [0u8];
+        stream.read_exact(&mut nul).unwrap();
+        assert_eq!(nul, [0]);
+        // SAFETY: getuid has no preconditions and cannot fail
+        let uid = unsafe { libc::getuid() };
+        let hex_uid: String = uid
+            .to_string()
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(
+            read_auth_line(&mut stream),
+            format!("AUTH EXTERNAL {}", hex_uid)
+        );
+        stream.write_all(b"OK 0123456789abcdef\r\n").unwrap();
+        assert_eq!(read_auth_line(&mut stream), "BEGIN");
+
+        let mut peer = Connection { stream, serial: 0 };
+        let hello = ("Hello", vec![Arg::Str(":1.7".to_string())]);
+        for (member, reply) in std::iter::once(hello).chain(answers) {
+            let call = peer.read_message().unwrap();
+            assert_eq!(call.kind, METHOD_CALL);
+            assert_eq!(call.member.as_deref(), Some(member));
+            peer.serial += 1;
+            let message = encode_reply(peer.serial, call.serial, &reply);
+            peer.stream.write_all(&message).unwrap();
+        }
+    }
+
+    #[test]
+    fn signs_in_says_hello_and_gets_a_notification_id() {
+        let (client, bus) = UnixStream::pair().unwrap();
+        let bus = thread::spawn(move || {
+            fake_bus(bus, vec![("Notify", vec![Arg::U32(42)])]);
+        });
+        let mut connection = Connection::open(client).unwrap();
+        let serial = connection
+            .call(
+                "org.freedesktop.Notifications",
+                "/org/freedesktop/Notifications",
+                "org.freedesktop.Notifications",
+                "Notify",
+                &[
+                    Arg::Str("pomo".to_string()),
+                    Arg::U32(0),
+                    Arg::Str(String::new()),
+                    Arg::Str("Time for a rest".to_string()),
+                    Arg::Str("writing · 5m, then focus".to_string()),
+                    Arg::StrArray(vec!["skip_break".to_string(), "Skip break".to_string()]),
+                    Arg::Dict(vec![("urgency".to_string(), Arg::Byte(1))]),
+                    Arg::I32(-1),
+                ],
+            )
+            .unwrap();
+        let reply = connection.read_message().unwrap();
+        assert_eq!(reply.kind, METHOD_RETURN);
+        assert_eq!(reply.reply_serial, Some(serial));
+        assert_eq!(reply.body, vec![Arg::U32(42)]);
+        bus.join().unwrap();
+    }
+
+    #[test]
+    fn reads_back_the_basic_arguments_it_writes() {
+        let (one, other) = UnixStream::pair().unwrap();
+        let mut sender = Connection {
+            stream: one,
+            serial: 0,
+        };
+        let mut receiver = Connection {
+            stream: other,
+            serial: 0,
+        };
+        let args = vec![
+            Arg::Byte(2),
+            Arg::U32(7),
+            Arg::I32(-1),
+            Arg::Str("skip_break".to_string()),
+        ];
+        let serial = sender
+            .call(
+                "org.example",
+                "/org/example",
+                "org.example.Test",
+                "Ping",
+                &args,
+            )
+            .unwrap();
+        let message = receiver.read_message().unwrap();
+        assert_eq!(message.kind, METHOD_CALL);
+        assert_eq!(message.serial, serial);
+        assert_eq!(message.interface.as_deref(), Some("org.example.Test"));
+        assert_eq!(message.member.as_deref(), Some("Ping"));
+        assert_eq!(message.body, args);
+    }
+
+    #[test]
+    fn gives_up_on_a_bus_that_never_answers() {
+        let (client, _silent_bus) = UnixStream::pair().unwrap();
+        let started = Instant::now();
+        assert!(Connection::open(client).is_err());
+        assert!(started.elapsed() < TIMEOUT * 2);
+    }
+}
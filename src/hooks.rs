@@ -0,0 +1,351 @@
+// user hooks: an executable in the `hooks` folder of the data directory,
+// named after an event, runs whenever that event happens. It gets the
+// details as POMO_* environment variables and as a JSON object on stdin.
+//
+// hooks run one at a time, in order, on a worker thread, so a slow hook
+// never holds up the timer; one that outlives hook_timeout is killed.
+// Their output, failures and timeouts go to hooks/hooks.log.
+use crate::config;
+use crate::output::{self, Record, Value};
+use crate::session::{Clock, Event, Session};
+use crate::tracker::{Phase, ProjectTrackerDb, SessionOutcome, TimerSettings, unix_now};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const HOOKS_DIR: &str = "hooks";
+const LOG_FILE: &str = "hooks.log";
+// how long a hook may run unless hook_timeout says otherwise
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    SessionStart,
+    SessionEnd,
+    FocusStart,
+    FocusEnd,
+    RestStart,
+    RestEnd,
+    LongRestStart,
+    LongRestEnd,
+    Pause,
+    Resume,
+    Skip,
+    Quit,
+    ProjectCreated,
+    ProjectDeleted,
+}
+impl Hook {
+    pub const ALL: [Hook; 14] = [
+        Hook::SessionStart,
+        Hook::SessionEnd,
+        Hook::FocusStart,
+        Hook::FocusEnd,
+        Hook::RestStart,
+        Hook::RestEnd,
+        Hook::LongRestStart,
+        Hook::LongRestEnd,
+        Hook::Pause,
+        Hook::Resume,
+        Hook::Skip,
+        Hook::Quit,
+        Hook::ProjectCreated,
+        Hook::ProjectDeleted,
+    ];
+    // also the file name of the hook
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::SessionStart => "session_start",
+            Hook::SessionEnd => "session_end",
+            Hook::FocusStart => "focus_start",
+            Hook::FocusEnd => "focus_end",
+            Hook::RestStart => "rest_start",
+            Hook::RestEnd => "rest_end",
+            Hook::LongRestStart => "long_rest_start",
+            Hook::LongRestEnd => "long_rest_end",
+            Hook::Pause => "pause",
+            Hook::Resume => "resume",
+            Hook::Skip => "skip",
+            Hook::Quit => "quit",
+            Hook::ProjectCreated => "project_created",
+            Hook::ProjectDeleted => "project_deleted",
+        }
+    }
+    fn phase_start(phase: Phase) -> Self {
+        match phase {
+            Phase::Focus => Hook::FocusStart,
+            Phase::Rest => Hook::RestStart,
+            Phase::LongRest => Hook::LongRestStart,
+        }
+    }
+    fn phase_end(phase: Phase) -> Self {
+        match phase {
+            Phase::Focus => Hook::FocusEnd,
+            Phase::Rest => Hook::RestEnd,
+            Phase::LongRest => Hook::LongRestEnd,
+        }
+    }
+}
+
+struct Job {
+    name: &'static str,
+    // local time, for the log
+    fired_at: String,
+    path: PathBuf,
+    log_path: PathBuf,
+    env: Vec<(String, String)>,
+    payload: String,
+    timeout: Duration,
+}
+
+struct Queue {
+    sender: Sender<Job>,
+    worker: JoinHandle<()>,
+}
+
+static QUEUE: Mutex<Option<Queue>> = Mutex::new(None);
+
+pub fn hooks_dir(project_tracker_data: &ProjectTrackerDb) -> PathBuf {
+    project_tracker_data.data_dir_getter().join(HOOKS_DIR)
+}
+
+// the executable for `hook`, if one is installed
+pub fn hook_path(project_tracker_data: &ProjectTrackerDb, hook: Hook) -> Option<PathBuf> {
+    let path = hooks_dir(project_tracker_data).join(hook.name());
+    path.is_file().then_some(path)
+}
+
+// queues the hook for `hook` with these details; does nothing if there is none
+pub fn fire(project_tracker_data: &ProjectTrackerDb, hook: Hook, details: Record) {
+    let Some(path) = hook_path(project_tracker_data, hook) else {
+        return;
+    };
+    let timeout = config::get(project_tracker_data, "hook_timeout")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT);
+
+    let mut record: Record = vec![
+        ("event", Value::Str(hook.name().to_string())),
+        ("time", Value::Int(unix_now())),
+    ];
+    record.extend(details);
+    let mut env = vec![(
+        "POMO_DATA_DIR".to_string(),
+        project_tracker_data
+            .data_dir_getter()
+            .to_string_lossy()
+            .to_string(),
+    )];
+    for (key, value) in record.iter() {
+        let text = match value {
+            Value::Str(s) => s.clone(),
+            Value::Int(n) => n.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Bool(b) => b.to_string(),
+        };
+        env.push((format!("POMO_{}", key.to_ascii_uppercase()), text));
+    }
+    let job = Job {
+        name: hook.name(),
+        fired_at: project_tracker_data
+            .format_local_time(unix_now())
+            .unwrap_or_default(),
+        path,
+        log_path: hooks_dir(project_tracker_data).join(LOG_FILE),
+        env,
+        payload: output::json_object(&record),
+        timeout,
+    };
+
+    let mut queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+    let queue = queue.get_or_insert_with(|| {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let worker = thread::spawn(move || {
+            for job in receiver {
+                run(job);
+            }
+        });
+        Queue { sender, worker }
+    });
+    let _ = queue.sender.send(job);
+}
+
+// waits for the queued hooks to finish (or time out) before pomo exits
+pub fn finish() {
+    let queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(Queue { sender, worker }) = queue {
+        drop(sender);
+        let _ = worker.join();
+    }
+}
+
+fn run(job: Job) {
+    let Ok(log) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&job.log_path)
+    else {
+        return;
+    };
+    let note = |log: &File, message: String| {
+        let mut log = log;
+        let _ = writeln!(log, "[{}] {}: {}", job.fired_at, job.name, message);
+    };
+    let (Ok(stdout), Ok(stderr)) = (log.try_clone(), log.try_clone()) else {
+        return;
+    };
+    let mut child = match Command::new(&job.path)
+        .envs(job.env.clone())
+        .current_dir(job.path.parent().unwrap_or(Path::new(".")))
+        .stdin(Stdio::piped())
+        .stdout(stdout)
+        .stderr(stderr)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            note(&log, format!("failed to run {}: {}", job.path.display(), e));
+            return;
+        }
+    };
+    // a hook that doesn't read its stdin just closes the pipe
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(job.payload.as_bytes());
+        let _ = stdin.write_all(b"\n");
+    }
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return,
+            Ok(Some(status)) => {
+                note(&log, format!("exited with {}", status));
+                return;
+            }
+            Ok(None) if started.elapsed() >= job.timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                note(
+                    &log,
+                    format!("killed after running {}s", job.timeout.as_secs()),
+                );
+                return;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(e) => {
+                note(&log, format!("failed to wait for the hook: {}", e));
+                return;
+            }
+        }
+    }
+}
+
+pub fn session_started(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+    settings: &TimerSettings,
+) {
+    fire(
+        project_tracker_data,
+        Hook::SessionStart,
+        vec![
+            ("project", Value::Str(project_name.to_string())),
+            ("focus_seconds", Value::Int(settings.focus_seconds as i64)),
+            ("rest_seconds", Value::Int(settings.rest_seconds as i64)),
+            (
+                "long_rest_seconds",
+                Value::Int(settings.long_rest_seconds as i64),
+            ),
+            ("flow", Value::Bool(settings.flow)),
+        ],
+    );
+}
+
+// the hooks that follow from one event of a running session; call it once
+// update() has returned, so the session already shows the phase it moved to
+pub fn session_event<C: Clock>(
+    project_tracker_data: &ProjectTrackerDb,
+    session: &Session<C>,
+    event: &Event,
+) {
+    let project = ("project", Value::Str(session.project_name().to_string()));
+    match event {
+        Event::PhaseStarted { phase, cycle } => fire(
+            project_tracker_data,
+            Hook::phase_start(*phase),
+            vec![
+                project,
+                ("phase", Value::Str(phase.as_str().to_string())),
+                ("cycle", Value::Int(*cycle as i64)),
+                (
+                    "planned_seconds",
+                    Value::Int(session.planned_seconds() as i64),
+                ),
+                ("flow", Value::Bool(session.is_flow())),
+            ],
+        ),
+        Event::Paused | Event::Resumed => {
+            let hook = if matches!(event, Event::Paused) {
+                Hook::Pause
+            } else {
+                Hook::Resume
+            };
+            fire(
+                project_tracker_data,
+                hook,
+                vec![
+                    project,
+                    ("phase", Value::Str(session.phase().as_str().to_string())),
+                    (
+                        "elapsed_seconds",
+                        Value::Int(session.elapsed().as_secs() as i64),
+                    ),
+                    (
+                        "remaining_seconds",
+                        Value::Int(session.remaining().as_secs() as i64),
+                    ),
+                ],
+            );
+        }
+        Event::PhaseEnded(entry) => {
+            let details = || {
+                vec![
+                    ("project", Value::Str(session.project_name().to_string())),
+                    ("phase", Value::Str(entry.phase.as_str().to_string())),
+                    ("started_at", Value::Int(entry.started_at)),
+                    ("ended_at", Value::Int(entry.ended_at)),
+                    ("elapsed_seconds", Value::Int(entry.elapsed_seconds as i64)),
+                    ("outcome", Value::Str(entry.outcome.as_str().to_string())),
+                    ("adjustment_seconds", Value::Int(entry.adjustment_seconds)),
+                ]
+            };
+            match entry.outcome {
+                SessionOutcome::Skipped => fire(project_tracker_data, Hook::Skip, details()),
+                SessionOutcome::Quit => fire(project_tracker_data, Hook::Quit, details()),
+                SessionOutcome::Completed => {}
+            }
+            fire(
+                project_tracker_data,
+                Hook::phase_end(entry.phase),
+                details(),
+            );
+        }
+        Event::Ended { target_reached } => fire(
+            project_tracker_data,
+            Hook::SessionEnd,
+            vec![
+                project,
+                ("focus_seconds", Value::Int(session.focus_seconds() as i64)),
+                ("target_reached", Value::Bool(*target_reached)),
+            ],
+        ),
+        Event::ClockJumped { .. } | Event::Adjusted { .. } => {}
+    }
+}
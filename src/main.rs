@@ -1,26 +1,70 @@
+use crate::args::Command;
 use crate::cli::{action_router, action_selection};
-use std::path::PathBuf;
+pub mod args;
 pub mod cli;
+pub mod commands;
+pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+#[cfg(unix)]
+pub mod dbus;
+pub mod hooks;
+pub mod migrations;
+pub mod notify;
+pub mod output;
+pub mod paths;
 pub mod pomodoro;
+pub mod session;
+pub mod statusline;
+pub mod terminal;
 pub mod tracker;
 
-fn get_database_path() -> PathBuf {
-    // Use proper platform-specific data directory
-    let mut db_path = dirs::data_dir().expect("Could not find data directory");
-    db_path.push("pomo");
-    std::fs::create_dir_all(&db_path).expect("Failed to create data directory");
-    db_path.push("pomodoro.db");
-    db_path
-}
-
 fn main() {
-    cli::welcome();
-    let db_path = get_database_path();
-    let project_db = tracker::ProjectTrackerDb::new(db_path.to_str().unwrap())
-        .expect("Error connecting database");
+    let invocation = match args::parse(std::env::args().skip(1)) {
+        Ok(invocation) => invocation,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Command::Help = invocation.command {
+        println!("{}", args::USAGE);
+        return;
+    }
+    let data_dir = match paths::resolve_data_dir(invocation.data_dir, invocation.portable) {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let interactive = matches!(invocation.command, Command::Menu);
+    if interactive {
+        cli::welcome();
+    }
+    let project_db = match tracker::ProjectTrackerDb::new(&data_dir) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("Error connecting database: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if !interactive {
+        let result = commands::run(invocation.command, &project_db);
+        // let queued hooks run before the process goes away
+        hooks::finish();
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Err(e) = cli::recover_active_session(&project_db) {
+        println!("{}", e);
+    }
     loop {
         let selected_action = action_selection();
         action_router(selected_action, &project_db);
-        println!("")
+        println!()
     }
 }
@@ -0,0 +1,512 @@
+// versioned schema upgrades, keyed on SQLite's PRAGMA user_version
+use crate::tracker::{db_error, normalize_project_name, project_key, unix_now};
+use rusqlite::{Connection, Result, params};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+type Migration = fn(&Connection) -> Result<()>;
+
+// MIGRATIONS[i] upgrades a database from version i to version i + 1.
+// Never edit or reorder a released step, only append new ones.
+const MIGRATIONS: &[Migration] = &[
+    create_projects,
+    create_sessions,
+    unique_project_names,
+    archived_projects,
+    audit_log,
+    integer_seconds,
+    relative_journal_paths,
+    active_session,
+    settings,
+    presets,
+    project_timer_defaults,
+    flowtime,
+    timer_adjustments,
+];
+
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+pub fn migrate(conn: &mut Connection, db_path: &str) -> Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > SCHEMA_VERSION {
+        return Err(db_error(format!(
+            "Database '{}' was written by a newer pomo (schema version {}, this build supports up to {}). Please upgrade pomo.",
+            db_path, version, SCHEMA_VERSION
+        )));
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    // databases from before versioning existed still report version 0
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_tables {
+        backup(conn, db_path, version)?;
+    }
+
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", step as i64 + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+// snapshot the database next to itself before touching the schema
+fn backup(conn: &Connection, db_path: &str, version: i64) -> Result<()> {
+    if db_path.is_empty() || db_path == ":memory:" {
+        return Ok(());
+    }
+    let backup_path = format!("{}.v{}-{}.bak", db_path, version, unix_now());
+    conn.execute("VACUUM INTO ?1", params![backup_path])?;
+    Ok(())
+}
+
+fn create_projects(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_with_journal (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_name TEXT NOT NULL,
+            time_invested FLOAT NOT NULL,
+            journal_path TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn create_sessions(conn: &Connection) -> Result<()> {
+    let has_sessions: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'sessions')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_sessions {
+        return Ok(());
+    }
+    conn.execute(
+        "CREATE TABLE sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_id INTEGER NOT NULL REFERENCES project_with_journal(id) ON DELETE CASCADE,
+            phase TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            ended_at INTEGER NOT NULL,
+            elapsed_seconds INTEGER NOT NULL,
+            outcome TEXT NOT NULL
+        )",
+        [],
+    )?;
+    // carry the old cumulative totals over as one session per project,
+    // so time_invested can be derived from the history from now on
+    conn.execute(
+        "INSERT INTO sessions (project_id, phase, started_at, ended_at, elapsed_seconds, outcome)
+         SELECT id, 'focus', ?1, ?1, CAST(ROUND(time_invested * 60) AS INTEGER), 'completed'
+         FROM project_with_journal WHERE time_invested > 0",
+        params![unix_now()],
+    )?;
+    Ok(())
+}
+
+// older versions accepted the same name twice; fold duplicates into the
+// oldest row (sessions and journal included) before enforcing uniqueness
+fn unique_project_names(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "ALTER TABLE project_with_journal ADD COLUMN name_key TEXT",
+        [],
+    )?;
+    let mut stmt = conn
+        .prepare("SELECT id, project_name, journal_path FROM project_with_journal ORDER BY id")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                PathBuf::from(row.get::<_, String>(2)?),
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut survivors: HashMap<String, (i64, PathBuf)> = HashMap::new();
+    for (id, name, journal_path) in rows {
+        let key = project_key(&name);
+        match survivors.get(&key) {
+            Some((survivor_id, survivor_journal)) => {
+                conn.execute(
+                    "UPDATE sessions SET project_id = ?1 WHERE project_id = ?2",
+                    params![survivor_id, id],
+                )?;
+                if journal_path != *survivor_journal {
+                    merge_journal(&journal_path, survivor_journal, &name)?;
+                }
+                conn.execute(
+                    "DELETE FROM project_with_journal WHERE id = ?1",
+                    params![id],
+                )?;
+            }
+            None => {
+                conn.execute(
+                    "UPDATE project_with_journal SET project_name = ?1, name_key = ?2 WHERE id = ?3",
+                    params![normalize_project_name(&name), key, id],
+                )?;
+                survivors.insert(key, (id, journal_path));
+            }
+        }
+    }
+
+    conn.execute(
+        "CREATE UNIQUE INDEX project_name_key ON project_with_journal(name_key)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn archived_projects(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "ALTER TABLE project_with_journal ADD COLUMN archived_at INTEGER",
+        [],
+    )?;
+    Ok(())
+}
+
+fn audit_log(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            created_at INTEGER NOT NULL,
+            action TEXT NOT NULL,
+            details TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// time_invested used to be a float of minutes; the session history now
+// holds every second, so the column goes and stray REAL values are rounded
+fn integer_seconds(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "UPDATE sessions SET elapsed_seconds = CAST(ROUND(elapsed_seconds) AS INTEGER)
+         WHERE typeof(elapsed_seconds) != 'integer'",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE project_with_journal DROP COLUMN time_invested",
+        [],
+    )?;
+    Ok(())
+}
+
+// journal paths used to be absolute; keep only the file name so the
+// whole data directory can be relocated
+fn relative_journal_paths(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT id, journal_path FROM project_with_journal")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                PathBuf::from(row.get::<_, String>(1)?),
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    for (id, journal_path) in rows {
+        if let Some(file_name) = journal_path.file_name() {
+            conn.execute(
+                "UPDATE project_with_journal SET journal_path = ?1 WHERE id = ?2",
+                params![file_name.to_string_lossy().to_string(), id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// a single row checkpointing the timer that is running right now
+fn active_session(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE active_session (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            project_id INTEGER NOT NULL REFERENCES project_with_journal(id) ON DELETE CASCADE,
+            phase TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            elapsed_seconds INTEGER NOT NULL,
+            paused INTEGER NOT NULL,
+            pause_count INTEGER NOT NULL,
+            cycle INTEGER NOT NULL,
+            rest_count INTEGER NOT NULL,
+            focus_seconds INTEGER NOT NULL,
+            rest_seconds INTEGER NOT NULL,
+            long_rest_seconds INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// key-value user preferences, plus the cycle rules of a running session
+fn settings(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE active_session ADD COLUMN long_break_interval INTEGER NOT NULL DEFAULT 4",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE active_session ADD COLUMN target_cycles INTEGER",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE active_session ADD COLUMN count_skipped_rests INTEGER NOT NULL DEFAULT 0",
+        [],
+    )?;
+    Ok(())
+}
+
+// named timer lengths, seeded with the common rhythms
+fn presets(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE presets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            name_key TEXT NOT NULL UNIQUE,
+            focus_seconds INTEGER NOT NULL,
+            rest_seconds INTEGER NOT NULL,
+            long_rest_seconds INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    for (name, focus, rest, long_rest) in [
+        ("classic", 25, 5, 15),
+        ("deep", 50, 10, 30),
+        ("sprint", 15, 3, 10),
+    ] {
+        conn.execute(
+            "INSERT INTO presets (name, name_key, focus_seconds, rest_seconds, long_rest_seconds)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                name,
+                project_key(name),
+                focus * 60,
+                rest * 60,
+                long_rest * 60
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+// a project's own timer lengths; NULL means it follows the global defaults
+fn project_timer_defaults(conn: &Connection) -> Result<()> {
+    for column in [
+        "focus_seconds",
+        "rest_seconds",
+        "long_rest_seconds",
+        "long_break_interval",
+    ] {
+        conn.execute(
+            &format!(
+                "ALTER TABLE project_with_journal ADD COLUMN {} INTEGER",
+                column
+            ),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn flowtime(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "ALTER TABLE active_session ADD COLUMN flow INTEGER NOT NULL DEFAULT 0",
+        [],
+    )?;
+    Ok(())
+}
+
+// minutes added to or taken off a running timer, kept with the finished
+// session and with the checkpoint so a resumed timer keeps them
+fn timer_adjustments(conn: &Connection) -> Result<()> {
+    for table in ["sessions", "active_session"] {
+        conn.execute(
+            &format!(
+                "ALTER TABLE {} ADD COLUMN adjustment_seconds INTEGER NOT NULL DEFAULT 0",
+                table
+            ),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn merge_journal(from: &PathBuf, into: &PathBuf, name: &str) -> Result<()> {
+    let io_error = |e: std::io::Error| {
+        db_error(format!(
+            "Failed to merge journal '{}': {}",
+            from.display(),
+            e
+        ))
+    };
+    if !from.exists() {
+        return Ok(());
+    }
+    let notes = std::fs::read_to_string(from).map_err(io_error)?;
+    if !notes.trim().is_empty() {
+        let mut journal = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(into)
+            .map_err(io_error)?;
+        write!(journal, "\n\n## Merged from '{}'\n\n{}", name, notes).map_err(io_error)?;
+    }
+    std::fs::remove_file(from).map_err(io_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracker::ProjectTrackerDb;
+    use std::path::Path;
+
+    // a fresh data directory of the test's own under the system temp dir
+    fn data_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pomo-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("journal")).unwrap();
+        dir
+    }
+
+    fn backups(dir: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.to_string_lossy().ends_with(".bak"))
+            .collect()
+    }
+
+    // the one table pomo had before versioning: float minutes, absolute
+    // journal paths and names that differ only in case or spacing
+    fn write_legacy_db(dir: &Path) {
+        let journal = |name: &str| dir.join("journal").join(name);
+        std::fs::write(journal("Writing.md"), "draft notes\n").unwrap();
+        std::fs::write(journal("writing-old.md"), "old notes\n").unwrap();
+        let conn = Connection::open(dir.join("pomodoro.db")).unwrap();
+        conn.execute(
+            "CREATE TABLE project_with_journal (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                project_name TEXT NOT NULL,
+                time_invested FLOAT NOT NULL,
+                journal_path TEXT NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        for (name, minutes, file) in [
+            ("Writing", 30.5, "Writing.md"),
+            ("writing  ", 12.25, "writing-old.md"),
+            ("Reading", 0.0, "Reading.md"),
+        ] {
+            conn.execute(
+                "INSERT INTO project_with_journal (project_name, time_invested, journal_path)
+                 VALUES (?1, ?2, ?3)",
+                params![name, minutes, journal(file).to_string_lossy()],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn upgrades_a_legacy_database() {
+        let dir = data_dir("legacy");
+        write_legacy_db(&dir);
+
+        let db = ProjectTrackerDb::new(&dir).unwrap();
+        let conn = Connection::open(dir.join("pomodoro.db")).unwrap();
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+
+        // the duplicate is folded into the oldest row, totals and all
+        let projects = db.get_projects().unwrap();
+        assert_eq!(projects.len(), 2);
+        let writing = db.get_single_project("WRITING").unwrap().unwrap();
+        assert_eq!(writing.name_getter(), "Writing");
+        assert_eq!(writing.time_getter().as_secs(), 1830 + 735);
+        let reading = db.get_single_project("Reading").unwrap().unwrap();
+        assert_eq!(reading.time_getter().as_secs(), 0);
+        let sessions: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sessions, 2);
+
+        // journal paths are relative, and the duplicate's notes moved over
+        let journal_path: String = conn
+            .query_row(
+                "SELECT journal_path FROM project_with_journal WHERE project_name = 'Writing'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(journal_path, "Writing.md");
+        assert_eq!(
+            writing.path_getter(),
+            &dir.join("journal").join("Writing.md")
+        );
+        let notes = std::fs::read_to_string(writing.path_getter()).unwrap();
+        assert!(notes.starts_with("draft notes\n"));
+        assert!(notes.contains("## Merged from 'writing  '\n\nold notes\n"));
+        assert!(!dir.join("journal").join("writing-old.md").exists());
+
+        // the untouched legacy database is kept beside the new one
+        let saved = backups(&dir);
+        assert_eq!(saved.len(), 1);
+        let name = saved[0].file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("pomodoro.db.v0-"));
+        let backup = Connection::open(&saved[0]).unwrap();
+        let total: f64 = backup
+            .query_row(
+                "SELECT SUM(time_invested) FROM project_with_journal",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(total, 42.75);
+
+        // opening it again changes nothing
+        drop(db);
+        ProjectTrackerDb::new(&dir).unwrap();
+        assert_eq!(backups(&dir).len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_new_database_needs_no_backup() {
+        let dir = data_dir("fresh");
+        let db = ProjectTrackerDb::new(&dir).unwrap();
+        db.create_project("Writing").unwrap();
+        assert!(backups(&dir).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_a_database_from_a_newer_pomo() {
+        let dir = data_dir("newer");
+        let conn = Connection::open(dir.join("pomodoro.db")).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        drop(conn);
+        let error = ProjectTrackerDb::new(&dir).err().unwrap();
+        assert!(error.to_string().contains("newer pomo"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
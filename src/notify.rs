@@ -0,0 +1,287 @@
+// desktop notifications at each phase change, sent to the freedesktop
+// notification service (org.freedesktop.Notifications) on the session bus.
+// Their buttons, like "Skip break", come back as inputs for the session.
+// Without a session bus or a notification service there are none, and the
+// bell is all there is.
+use crate::config;
+use crate::session::{Clock, Event, Input, Session};
+use crate::tracker::ProjectTrackerDb;
+
+pub struct Notifications {
+    #[cfg(unix)]
+    desktop: Option<desktop::Desktop>,
+}
+
+impl Notifications {
+    // connects unless the desktop_notifications setting is off
+    pub fn new(project_tracker_data: &ProjectTrackerDb) -> Self {
+        let enabled = config::get(project_tracker_data, "desktop_notifications")
+            .ok()
+            .flatten()
+            .is_none_or(|value| value == "true");
+        #[cfg(unix)]
+        let notifications = Notifications {
+            desktop: enabled.then(desktop::Desktop::connect).flatten(),
+        };
+        #[cfg(not(unix))]
+        let notifications = {
+            let _ = enabled;
+            Notifications {}
+        };
+        notifications
+    }
+    // false once it is clear that no notification reaches the desktop
+    pub fn is_available(&self) -> bool {
+        #[cfg(unix)]
+        return self
+            .desktop
+            .as_ref()
+            .is_some_and(|desktop| desktop.is_available());
+        #[cfg(not(unix))]
+        false
+    }
+    // call it once update() has returned, so the session shows the phase it moved to
+    pub fn on_event<C: Clock>(&mut self, session: &Session<C>, event: &Event) {
+        #[cfg(unix)]
+        if let Some(desktop) = self.desktop.as_mut() {
+            desktop.on_event(session, event);
+        }
+        #[cfg(not(unix))]
+        let _ = (session, event);
+    }
+    // the input of a button pressed on the latest notification, if any
+    pub fn take_input<C: Clock>(&mut self, session: &Session<C>) -> Option<Input> {
+        #[cfg(unix)]
+        return self
+            .desktop
+            .as_mut()
+            .and_then(|desktop| desktop.take_input(session));
+        #[cfg(not(unix))]
+        {
+            let _ = session;
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+mod desktop {
+    use crate::cli::format_duration;
+    use crate::dbus::{self, Arg, Connection};
+    use crate::session::{Clock, Event, Input, Session};
+    use crate::tracker::Phase;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+
+    const SERVICE: &str = "org.freedesktop.Notifications";
+    const PATH: &str = "/org/freedesktop/Notifications";
+
+    // urgency levels of the notification spec
+    const LOW: u8 = 0;
+    const NORMAL: u8 = 1;
+    const CRITICAL: u8 = 2;
+
+    pub struct Desktop {
+        connection: Connection,
+        // state shared with the thread that reads the bus
+        shared: Arc<Shared>,
+        actions: Receiver<(u32, String)>,
+    }
+
+    #[derive(Default)]
+    struct Shared {
+        // serial of the last Notify call, to know its reply when it comes
+        notify_serial: AtomicU32,
+        // the server's id for our notification; each one replaces the last
+        id: AtomicU32,
+        // cleared when the bus says nobody serves notifications
+        unavailable: AtomicBool,
+    }
+
+    impl Desktop {
+        pub fn connect() -> Option<Self> {
+            let mut connection = Connection::session().ok()?;
+            connection
+                .call(
+                    "org.freedesktop.DBus",
+                    "/org/freedesktop/DBus",
+                    "org.freedesktop.DBus",
+                    "AddMatch",
+                    &[Arg::Str(format!(
+                        "type='signal',interface='{}',member='ActionInvoked'",
+                        SERVICE
+                    ))],
+                )
+                .ok()?;
+            let shared = Arc::new(Shared::default());
+            let (sender, actions) = mpsc::channel();
+            let mut reader = connection.try_clone().ok()?;
+            let thread_shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                while let Ok(message) = reader.read_message() {
+                    let replies_to_notify = message.reply_serial
+                        == Some(thread_shared.notify_serial.load(Ordering::SeqCst));
+                    match (message.kind, message.body.as_slice()) {
+                        (dbus::METHOD_RETURN, [Arg::U32(id)]) if replies_to_notify => {
+                            thread_shared.id.store(*id, Ordering::SeqCst);
+                        }
+                        (dbus::ERROR, _) if replies_to_notify => {
+                            thread_shared.unavailable.store(true, Ordering::SeqCst);
+                        }
+                        (dbus::SIGNAL, [Arg::U32(id), Arg::Str(key)])
+                            if message.member.as_deref() == Some("ActionInvoked") =>
+                        {
+                            let _ = sender.send((*id, key.clone()));
+                        }
+                        _ => {}
+                    }
+                }
+            });
+            Some(Desktop {
+                connection,
+                shared,
+                actions,
+            })
+        }
+
+        pub fn is_available(&self) -> bool {
+            !self.shared.unavailable.load(Ordering::SeqCst)
+        }
+
+        pub fn on_event<C: Clock>(&mut self, session: &Session<C>, event: &Event) {
+            let project = session.project_name();
+            match event {
+                Event::PhaseStarted { phase, cycle } => {
+                    let length =
+                        format_duration(std::time::Duration::from_secs(session.planned_seconds()));
+                    let next = match session.next_phase() {
+                        Phase::Focus => "focus",
+                        Phase::Rest => "a rest",
+                        Phase::LongRest => "a long rest",
+                    };
+                    match phase {
+                        Phase::Focus if session.is_counting_up() => self.notify(
+                            "Flowtime focus",
+                            &format!(
+                                "{} · cycle {} · stop when you lose flow, then {}",
+                                project, cycle, next
+                            ),
+                            NORMAL,
+                            &[("start_break", "Stop and rest")],
+                        ),
+                        Phase::Focus if *cycle == 1 => self.notify(
+                            "Focus started",
+                            &format!("{} · {}, then {}", project, length, next),
+                            NORMAL,
+                            &[("start_break", "Start break")],
+                        ),
+                        // the break is over: worth interrupting for
+                        Phase::Focus => self.notify(
+                            "Back to focus",
+                            &format!("{} · cycle {} · {}, then {}", project, cycle, length, next),
+                            CRITICAL,
+                            &[("start_break", "Start break")],
+                        ),
+                        Phase::Rest | Phase::LongRest => self.notify(
+                            if *phase == Phase::Rest {
+                                "Time for a rest"
+                            } else {
+                                "Time for a long rest"
+                            },
+                            &format!("{} · {}, then {}", project, length, next),
+                            NORMAL,
+                            &[("skip_break", "Skip break"), ("snooze", "Snooze 5 min")],
+                        ),
+                    }
+                }
+                Event::Ended {
+                    target_reached: true,
+                } => {
+                    let focus =
+                        format_duration(std::time::Duration::from_secs(session.focus_seconds()));
+                    self.notify(
+                        "All cycles done",
+                        &format!("{} · {} of focus recorded", project, focus),
+                        LOW,
+                        &[],
+                    );
+                }
+                // stopped by hand: its buttons would do nothing now
+                Event::Ended {
+                    target_reached: false,
+                } => self.close(),
+                _ => {}
+            }
+        }
+
+        fn notify(&mut self, summary: &str, body: &str, urgency: u8, actions: &[(&str, &str)]) {
+            let actions = actions
+                .iter()
+                .flat_map(|(key, label)| [key.to_string(), label.to_string()])
+                .collect();
+            self.shared
+                .notify_serial
+                .store(self.connection.next_serial(), Ordering::SeqCst);
+            let sent = self.connection.call(
+                SERVICE,
+                PATH,
+                SERVICE,
+                "Notify",
+                &[
+                    Arg::Str("pomo".to_string()),
+                    Arg::U32(self.shared.id.load(Ordering::SeqCst)),
+                    Arg::Str(String::new()),
+                    Arg::Str(summary.to_string()),
+                    Arg::Str(body.to_string()),
+                    Arg::StrArray(actions),
+                    Arg::Dict(vec![("urgency".to_string(), Arg::Byte(urgency))]),
+                    Arg::I32(-1),
+                ],
+            );
+            if sent.is_err() {
+                self.shared.unavailable.store(true, Ordering::SeqCst);
+            }
+        }
+
+        fn close(&mut self) {
+            let id = self.shared.id.load(Ordering::SeqCst);
+            if id != 0 {
+                let _ = self.connection.call(
+                    SERVICE,
+                    PATH,
+                    SERVICE,
+                    "CloseNotification",
+                    &[Arg::U32(id)],
+                );
+            }
+        }
+
+        // buttons of older notifications, or ones that no longer fit the
+        // phase, are ignored
+        pub fn take_input<C: Clock>(&mut self, session: &Session<C>) -> Option<Input> {
+            let id = self.shared.id.load(Ordering::SeqCst);
+            let mut input = None;
+            while let Ok((action_id, key)) = self.actions.try_recv() {
+                if action_id != id || input.is_some() {
+                    continue;
+                }
+                input = match (key.as_str(), session.phase()) {
+                    ("start_break", Phase::Focus) => Some(Input::Skip),
+                    ("skip_break", Phase::Rest | Phase::LongRest) => Some(Input::Skip),
+                    ("snooze", Phase::Rest | Phase::LongRest) => Some(Input::Snooze),
+                    _ => None,
+                };
+            }
+            input
+        }
+    }
+
+    impl Drop for Desktop {
+        // lets the reading thread go
+        fn drop(&mut self) {
+            self.connection.shutdown();
+        }
+    }
+}
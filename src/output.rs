@@ -0,0 +1,240 @@
+// machine-readable renderings of pomo data: JSON, CSV and TSV
+//
+// JSON documents are objects carrying a "schema" tag (e.g. "pomo.status.v1")
+// and one array of records; CSV/TSV print the same records with a header row.
+// Fields are only ever added to a schema version, never renamed or removed.
+use crate::tracker::{ProjectTracker, SessionRecord};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+    Tsv,
+}
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!(
+                "Unknown format '{}'. Use text, json, csv or tsv.",
+                name
+            )),
+        }
+    }
+}
+
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+pub type Record = Vec<(&'static str, Value)>;
+
+// a document of `records` under `list_key`, plus top-level `summary` fields (JSON only)
+pub struct Document<'a> {
+    pub schema: &'a str,
+    pub summary: Record,
+    pub list_key: &'a str,
+    pub columns: &'a [&'static str],
+    pub records: Vec<Record>,
+}
+
+pub fn render(format: Format, document: &Document) -> String {
+    match format {
+        Format::Json => render_json(document),
+        Format::Csv => render_delimited(document, ','),
+        // text callers print their own layout; fall back to TSV
+        Format::Tsv | Format::Text => render_delimited(document, '\t'),
+    }
+}
+
+fn render_json(document: &Document) -> String {
+    let mut out = format!("{{\"schema\":{}", json_string(document.schema));
+    for (key, value) in document.summary.iter() {
+        out.push_str(&format!(",{}:{}", json_string(key), json_value(value)));
+    }
+    out.push_str(&format!(",{}:[", json_string(document.list_key)));
+    for (i, record) in document.records.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&json_object(record));
+    }
+    out.push_str("]}");
+    out
+}
+
+pub fn json_object(record: &Record) -> String {
+    let fields: Vec<String> = record
+        .iter()
+        .map(|(key, value)| format!("{}:{}", json_string(key), json_value(value)))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Str(s) => json_string(s),
+        Value::Int(n) => n.to_string(),
+        Value::Float(f) if f.is_finite() => format!("{}", f),
+        Value::Float(_) => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn render_delimited(document: &Document, delimiter: char) -> String {
+    let mut lines = vec![document.columns.join(&delimiter.to_string())];
+    for record in document.records.iter() {
+        let fields: Vec<String> = document
+            .columns
+            .iter()
+            .map(|column| {
+                let text = match record.iter().find(|(key, _)| key == column) {
+                    Some((_, Value::Str(s))) => s.clone(),
+                    Some((_, Value::Int(n))) => n.to_string(),
+                    Some((_, Value::Float(f))) => f.to_string(),
+                    Some((_, Value::Bool(b))) => b.to_string(),
+                    None => String::new(),
+                };
+                escape_field(&text, delimiter)
+            })
+            .collect();
+        lines.push(fields.join(&delimiter.to_string()));
+    }
+    lines.join("\n")
+}
+
+fn escape_field(text: &str, delimiter: char) -> String {
+    if delimiter == '\t' {
+        // TSV has no quoting; tabs and newlines inside a field become spaces
+        return text.replace(['\t', '\n', '\r'], " ");
+    }
+    if text.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// pomo.status.v1: active projects with their share of the total focus time
+pub fn status_document(project_list: &[ProjectTracker]) -> Document<'static> {
+    let total: u64 = project_list.iter().map(|p| p.time_getter().as_secs()).sum();
+    Document {
+        schema: "pomo.status.v1",
+        summary: vec![("total_seconds", Value::Int(total as i64))],
+        list_key: "projects",
+        columns: &["name", "seconds", "share"],
+        records: project_list
+            .iter()
+            .map(|project| {
+                let seconds = project.time_getter().as_secs();
+                let share = if total == 0 {
+                    0.0
+                } else {
+                    seconds as f64 / total as f64
+                };
+                vec![
+                    ("name", Value::Str(project.name_getter().to_string())),
+                    ("seconds", Value::Int(seconds as i64)),
+                    ("share", Value::Float(share)),
+                ]
+            })
+            .collect(),
+    }
+}
+
+// pomo.projects.v1
+pub fn projects_document(project_list: &[ProjectTracker]) -> Document<'static> {
+    Document {
+        schema: "pomo.projects.v1",
+        summary: Vec::new(),
+        list_key: "projects",
+        columns: &["name", "seconds", "archived", "journal_path"],
+        records: project_list
+            .iter()
+            .map(|project| {
+                vec![
+                    ("name", Value::Str(project.name_getter().to_string())),
+                    (
+                        "seconds",
+                        Value::Int(project.time_getter().as_secs() as i64),
+                    ),
+                    ("archived", Value::Bool(project.archived_getter())),
+                    (
+                        "journal_path",
+                        Value::Str(project.path_getter().to_string_lossy().to_string()),
+                    ),
+                ]
+            })
+            .collect(),
+    }
+}
+
+// pomo.sessions.v1: timestamps are unix seconds (UTC)
+pub fn sessions_document(sessions: &[SessionRecord]) -> Document<'static> {
+    Document {
+        schema: "pomo.sessions.v1",
+        summary: Vec::new(),
+        list_key: "sessions",
+        columns: &[
+            "id",
+            "project",
+            "phase",
+            "started_at",
+            "ended_at",
+            "elapsed_seconds",
+            "outcome",
+            "adjustment_seconds",
+        ],
+        records: sessions.iter().map(session_record).collect(),
+    }
+}
+
+pub fn session_record(session: &SessionRecord) -> Record {
+    vec![
+        ("id", Value::Int(session.id)),
+        ("project", Value::Str(session.project_name.clone())),
+        (
+            "phase",
+            Value::Str(session.entry.phase.as_str().to_string()),
+        ),
+        ("started_at", Value::Int(session.entry.started_at)),
+        ("ended_at", Value::Int(session.entry.ended_at)),
+        (
+            "elapsed_seconds",
+            Value::Int(session.entry.elapsed_seconds as i64),
+        ),
+        (
+            "outcome",
+            Value::Str(session.entry.outcome.as_str().to_string()),
+        ),
+        (
+            "adjustment_seconds",
+            Value::Int(session.entry.adjustment_seconds),
+        ),
+    ]
+}
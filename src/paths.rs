@@ -0,0 +1,59 @@
+// decides where pomo keeps its database and journals
+use std::path::{Path, PathBuf};
+
+// the daemon's control socket, inside the data directory
+const SOCKET_FILE: &str = "pomo.sock";
+
+// a directory with this name next to the executable switches on portable mode
+const PORTABLE_DIR: &str = "pomo-data";
+
+// first match wins: --data-dir, $POMO_HOME, portable mode, the platform data directory
+pub fn resolve_data_dir(data_dir_flag: Option<PathBuf>, portable: bool) -> Result<PathBuf, String> {
+    let data_dir = if let Some(dir) = data_dir_flag {
+        dir
+    } else if let Some(dir) = std::env::var_os("POMO_HOME").filter(|dir| !dir.is_empty()) {
+        PathBuf::from(dir)
+    } else if let Some(dir) = portable_dir(portable)? {
+        dir
+    } else {
+        // Use proper platform-specific data directory
+        let mut dir = dirs::data_dir()
+            .ok_or("Could not find data directory. Set POMO_HOME or pass --data-dir.")?;
+        dir.push("pomo");
+        dir
+    };
+    std::fs::create_dir_all(&data_dir).map_err(|e| {
+        format!(
+            "Failed to create data directory '{}': {}",
+            data_dir.display(),
+            e
+        )
+    })?;
+    Ok(data_dir)
+}
+
+// --portable creates the directory; once it exists it is picked up on its own.
+// Without --portable, not finding the executable just means no portable mode.
+fn portable_dir(portable: bool) -> Result<Option<PathBuf>, String> {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) if portable => {
+            return Err(format!("Could not locate the pomo executable: {}", e));
+        }
+        Err(_) => return Ok(None),
+    };
+    let Some(exe_dir) = exe.parent() else {
+        return Ok(None);
+    };
+    let dir = exe_dir.join(PORTABLE_DIR);
+    if portable || dir.is_dir() {
+        Ok(Some(dir))
+    } else {
+        Ok(None)
+    }
+}
+
+// where the daemon of a data directory listens
+pub fn socket_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SOCKET_FILE)
+}
@@ -1,49 +1,517 @@
 //the module acts as a state manager according to the cli commands
+use crate::hooks::{self, Hook};
+use crate::output::Value;
+use crate::tracker::ActiveSession;
+use crate::tracker::Preset;
+use crate::tracker::ProjectTimerDefaults;
 use crate::tracker::ProjectTracker;
 use crate::tracker::ProjectTrackerDb;
+use crate::tracker::SessionEntry;
+use crate::tracker::SessionRecord;
+use crate::tracker::{MAX_MINUTES, Phase, SessionOutcome, unix_now};
+use crate::tracker::{normalize_project_name, project_key};
 
-pub fn delete_project(
+pub fn archive_project(
     project_tracker_data: &ProjectTrackerDb,
     project_name: &str,
 ) -> Result<bool, String> {
-    match project_tracker_data.delete_project(project_name) {
-        Ok(deleted) => {
-            if deleted {
-                Ok(true)
-            } else {
-                Err(format!("Project '{}' not found.", project_name))
+    match project_tracker_data.archive_project(project_name) {
+        Ok(true) => {
+            project_deleted(project_tracker_data, project_name, false);
+            Ok(true)
+        }
+        Ok(false) => Err(format!("Project '{}' not found.", project_name)),
+        Err(e) => Err(format!("Error archiving project: {}", e)),
+    }
+}
+pub fn restore_project(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+) -> Result<bool, String> {
+    match project_tracker_data.restore_project(project_name) {
+        Ok(true) => Ok(true),
+        Ok(false) => Err(format!("No archived project named '{}'.", project_name)),
+        Err(e) => Err(format!("Error restoring project: {}", e)),
+    }
+}
+pub fn purge_project(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+) -> Result<bool, String> {
+    match project_tracker_data.purge_project(project_name) {
+        Ok(true) => {
+            project_deleted(project_tracker_data, project_name, true);
+            Ok(true)
+        }
+        Ok(false) => Err(format!(
+            "No archived project named '{}'. Only archived projects can be purged.",
+            project_name
+        )),
+        Err(e) => Err(format!("Error purging project: {}", e)),
+    }
+}
+// an archived project counts as deleted; `permanent` when it is purged
+fn project_deleted(project_tracker_data: &ProjectTrackerDb, project_name: &str, permanent: bool) {
+    hooks::fire(
+        project_tracker_data,
+        Hook::ProjectDeleted,
+        vec![
+            ("project", Value::Str(normalize_project_name(project_name))),
+            ("permanent", Value::Bool(permanent)),
+        ],
+    );
+}
+pub fn record_session(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+    entry: &SessionEntry,
+) -> Result<bool, String> {
+    match project_tracker_data.record_session(project_name, entry) {
+        Ok(true) => Ok(true),
+        Ok(false) => Err(format!("No such project named {}", project_name)),
+        Err(e) => Err(format!("Error recording session: {}", e)),
+    }
+}
+// record focus time spent away from the timer, refusing anything that
+// lies in the future, runs past a day or overlaps a recorded session
+pub fn log_manual_session(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+    started_at: i64,
+    ended_at: i64,
+) -> Result<bool, String> {
+    if ended_at <= started_at {
+        return Err("The session must end after it starts.".to_string());
+    }
+    if ended_at - started_at > (MAX_MINUTES * 60.0) as i64 {
+        return Err("A session can't be longer than 24 hours.".to_string());
+    }
+    if ended_at > unix_now() {
+        return Err("You can't log a session that ends in the future.".to_string());
+    }
+    get_project(project_tracker_data, project_name)?;
+    check_overlap(project_tracker_data, started_at, ended_at, None)?;
+    let entry = SessionEntry {
+        phase: Phase::Focus,
+        started_at,
+        ended_at,
+        elapsed_seconds: (ended_at - started_at) as u64,
+        outcome: SessionOutcome::Completed,
+        adjustment_seconds: 0,
+    };
+    record_session(project_tracker_data, project_name, &entry)
+}
+fn check_overlap(
+    project_tracker_data: &ProjectTrackerDb,
+    started_at: i64,
+    ended_at: i64,
+    excluding: Option<i64>,
+) -> Result<(), String> {
+    match project_tracker_data.find_overlapping_session(started_at, ended_at, excluding) {
+        Ok(Some(existing)) => {
+            let from = project_tracker_data
+                .format_local_time(existing.entry.started_at)
+                .map_err(|e| format!("Error formatting time: {}", e))?;
+            let to = project_tracker_data
+                .format_local_time(existing.entry.ended_at)
+                .map_err(|e| format!("Error formatting time: {}", e))?;
+            Err(format!(
+                "That overlaps a {} session on '{}' from {} to {}.",
+                existing.entry.phase.as_str(),
+                existing.project_name,
+                from,
+                to
+            ))
+        }
+        Ok(None) => Ok(()),
+        Err(e) => Err(format!("Error checking for overlapping sessions: {}", e)),
+    }
+}
+
+pub fn get_project_sessions(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+    limit: usize,
+) -> Result<Vec<SessionRecord>, String> {
+    match project_tracker_data.get_project_sessions(project_name, limit) {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("Error retrieving sessions: {}", e)),
+    }
+}
+
+pub fn get_recent_sessions(
+    project_tracker_data: &ProjectTrackerDb,
+    limit: usize,
+) -> Result<Vec<SessionRecord>, String> {
+    match project_tracker_data.get_recent_sessions(limit) {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("Error retrieving sessions: {}", e)),
+    }
+}
+
+pub fn save_active_session(
+    project_tracker_data: &ProjectTrackerDb,
+    active: &ActiveSession,
+) -> Result<bool, String> {
+    match project_tracker_data.save_active_session(active) {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("Error saving the running session: {}", e)),
+    }
+}
+
+pub fn get_active_session(
+    project_tracker_data: &ProjectTrackerDb,
+) -> Result<Option<ActiveSession>, String> {
+    match project_tracker_data.get_active_session() {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("Error retrieving the unfinished session: {}", e)),
+    }
+}
+
+pub fn clear_active_session(project_tracker_data: &ProjectTrackerDb) -> Result<(), String> {
+    match project_tracker_data.clear_active_session() {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Error clearing the running session: {}", e)),
+    }
+}
+
+pub fn get_presets(project_tracker_data: &ProjectTrackerDb) -> Result<Vec<Preset>, String> {
+    match project_tracker_data.get_presets() {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("Error retrieving presets: {}", e)),
+    }
+}
+
+pub fn get_preset(project_tracker_data: &ProjectTrackerDb, name: &str) -> Result<Preset, String> {
+    match project_tracker_data.get_preset(name) {
+        Ok(Some(preset)) => Ok(preset),
+        Ok(None) => Err(format!(
+            "Preset '{}' not found. Run 'pomo presets' to list them.",
+            name
+        )),
+        Err(e) => Err(format!("Error retrieving preset: {}", e)),
+    }
+}
+
+fn check_preset(preset: &Preset) -> Result<(), String> {
+    if normalize_project_name(&preset.name).is_empty() {
+        return Err("Preset name cannot be empty.".to_string());
+    }
+    if preset.focus_seconds == 0 || preset.rest_seconds == 0 || preset.long_rest_seconds == 0 {
+        return Err("Preset lengths must be above zero.".to_string());
+    }
+    Ok(())
+}
+
+pub fn create_preset(
+    project_tracker_data: &ProjectTrackerDb,
+    preset: &Preset,
+) -> Result<bool, String> {
+    check_preset(preset)?;
+    match project_tracker_data.create_preset(preset) {
+        Ok(_) => Ok(true),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err(format!(
+                "Preset '{}' already exists.",
+                normalize_project_name(&preset.name)
+            ))
+        }
+        Err(e) => Err(format!("Error creating preset: {}", e)),
+    }
+}
+
+// keeps the default pointing at the preset if it is renamed
+pub fn update_preset(
+    project_tracker_data: &ProjectTrackerDb,
+    name: &str,
+    preset: &Preset,
+) -> Result<bool, String> {
+    check_preset(preset)?;
+    let was_default = is_default_preset(project_tracker_data, name)?;
+    match project_tracker_data.update_preset(name, preset) {
+        Ok(true) => {
+            if was_default {
+                set_setting(
+                    project_tracker_data,
+                    "default_preset",
+                    &normalize_project_name(&preset.name),
+                )?;
             }
+            Ok(true)
+        }
+        Ok(false) => Err(format!("Preset '{}' not found.", name)),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err(format!(
+                "Preset '{}' already exists.",
+                normalize_project_name(&preset.name)
+            ))
         }
-        Err(e) => Err(format!("Error deleting project: {}", e)),
+        Err(e) => Err(format!("Error updating preset: {}", e)),
+    }
+}
+
+// deleting the default preset falls back to the built-in 25/5/15
+pub fn delete_preset(project_tracker_data: &ProjectTrackerDb, name: &str) -> Result<bool, String> {
+    let was_default = is_default_preset(project_tracker_data, name)?;
+    match project_tracker_data.delete_preset(name) {
+        Ok(true) => {
+            if was_default {
+                unset_setting(project_tracker_data, "default_preset")?;
+            }
+            Ok(true)
+        }
+        Ok(false) => Err(format!("Preset '{}' not found.", name)),
+        Err(e) => Err(format!("Error deleting preset: {}", e)),
+    }
+}
+
+fn is_default_preset(project_tracker_data: &ProjectTrackerDb, name: &str) -> Result<bool, String> {
+    Ok(get_setting(project_tracker_data, "default_preset")?
+        .is_some_and(|default| project_key(&default) == project_key(name)))
+}
+
+pub fn get_project_timer_defaults(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+) -> Result<Option<ProjectTimerDefaults>, String> {
+    match project_tracker_data.get_project_timer_defaults(project_name) {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!(
+            "Error retrieving the project's timer defaults: {}",
+            e
+        )),
     }
 }
-pub fn focus_on_project(
+
+pub fn set_project_timer_defaults(
     project_tracker_data: &ProjectTrackerDb,
     project_name: &str,
-    focus_time: f32,
+    defaults: Option<&ProjectTimerDefaults>,
 ) -> Result<bool, String> {
-    let previous_focus_time = match project_tracker_data.get_single_project(project_name) {
-        Ok(Some(valid_project)) => valid_project.time_getter(),
-        Ok(None) => return Err(format!("No such project named {}", project_name)),
-        Err(e) => return Err(format!("Error retrieving project: {}", e)),
+    match project_tracker_data.set_project_timer_defaults(project_name, defaults) {
+        Ok(true) => Ok(true),
+        Ok(false) => Err(format!("Project '{}' not found.", project_name)),
+        Err(e) => Err(format!("Error saving the project's timer defaults: {}", e)),
+    }
+}
+
+pub fn get_setting(
+    project_tracker_data: &ProjectTrackerDb,
+    key: &str,
+) -> Result<Option<String>, String> {
+    match project_tracker_data.get_setting(key) {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("Error reading setting '{}': {}", key, e)),
+    }
+}
+
+pub fn set_setting(
+    project_tracker_data: &ProjectTrackerDb,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    match project_tracker_data.set_setting(key, value) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Error saving setting '{}': {}", key, e)),
+    }
+}
+
+pub fn unset_setting(project_tracker_data: &ProjectTrackerDb, key: &str) -> Result<bool, String> {
+    match project_tracker_data.unset_setting(key) {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("Error removing setting '{}': {}", key, e)),
+    }
+}
+
+fn get_session(
+    project_tracker_data: &ProjectTrackerDb,
+    session_id: i64,
+) -> Result<SessionRecord, String> {
+    match project_tracker_data.get_session(session_id) {
+        Ok(Some(session)) => Ok(session),
+        Ok(None) => Err(format!("Session #{} not found.", session_id)),
+        Err(e) => Err(format!("Error retrieving session: {}", e)),
+    }
+}
+
+// the session keeps its start time and ends once the new duration has passed
+pub fn adjust_session_duration(
+    project_tracker_data: &ProjectTrackerDb,
+    session_id: i64,
+    elapsed_seconds: u64,
+) -> Result<bool, String> {
+    if elapsed_seconds == 0 {
+        return Err("A session must last at least a second. Delete it instead.".to_string());
+    }
+    let session = get_session(project_tracker_data, session_id)?;
+    let entry = SessionEntry {
+        ended_at: session.entry.started_at + elapsed_seconds as i64,
+        elapsed_seconds,
+        ..session.entry
     };
-    let time_invested = previous_focus_time + focus_time;
-    match project_tracker_data.update_project(project_name, time_invested) {
+    if entry.ended_at > unix_now() {
+        return Err("The session can't end in the future.".to_string());
+    }
+    check_overlap(
+        project_tracker_data,
+        entry.started_at,
+        entry.ended_at,
+        Some(session_id),
+    )?;
+    match project_tracker_data.update_session(session_id, &entry) {
+        Ok(true) => Ok(true),
+        Ok(false) => Err(format!("Session #{} not found.", session_id)),
+        Err(e) => Err(format!("Error updating session: {}", e)),
+    }
+}
+
+pub fn move_session(
+    project_tracker_data: &ProjectTrackerDb,
+    session_id: i64,
+    project_name: &str,
+) -> Result<bool, String> {
+    get_session(project_tracker_data, session_id)?;
+    match project_tracker_data.move_session(session_id, project_name) {
+        Ok(true) => Ok(true),
+        Ok(false) => Err(format!("No such project named {}", project_name)),
+        Err(e) => Err(format!("Error moving session: {}", e)),
+    }
+}
+
+pub fn split_session(
+    project_tracker_data: &ProjectTrackerDb,
+    session_id: i64,
+    first_seconds: u64,
+) -> Result<bool, String> {
+    let session = get_session(project_tracker_data, session_id)?;
+    if first_seconds == 0 || first_seconds >= session.entry.elapsed_seconds {
+        return Err("The first part must be shorter than the whole session.".to_string());
+    }
+    match project_tracker_data.split_session(session_id, first_seconds) {
+        Ok(Some(_)) => Ok(true),
+        Ok(None) => Err(format!("Session #{} not found.", session_id)),
+        Err(e) => Err(format!("Error splitting session: {}", e)),
+    }
+}
+
+pub fn delete_session(
+    project_tracker_data: &ProjectTrackerDb,
+    session_id: i64,
+) -> Result<bool, String> {
+    match project_tracker_data.delete_session(session_id) {
         Ok(true) => Ok(true),
-        Ok(false) => Err(format!("Project '{}' not found for update", project_name)),
-        Err(e) => Err(format!("Error updating project: {}", e)),
+        Ok(false) => Err(format!("Session #{} not found.", session_id)),
+        Err(e) => Err(format!("Error deleting session: {}", e)),
+    }
+}
+
+pub fn get_last_focus_session(
+    project_tracker_data: &ProjectTrackerDb,
+) -> Result<Option<SessionRecord>, String> {
+    match project_tracker_data.get_last_focus_session() {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("Error retrieving the last session: {}", e)),
     }
 }
+
+pub fn undo_session(
+    project_tracker_data: &ProjectTrackerDb,
+    session: &SessionRecord,
+) -> Result<bool, String> {
+    match project_tracker_data.undo_session(session) {
+        Ok(true) => Ok(true),
+        Ok(false) => Err(format!("Session #{} was already removed.", session.id)),
+        Err(e) => Err(format!("Error undoing session: {}", e)),
+    }
+}
+
+// names are stored with their whitespace collapsed, so a blank one would be empty
+fn check_project_name(project_name: &str) -> Result<(), String> {
+    if normalize_project_name(project_name).is_empty() {
+        return Err("Project name cannot be empty.".to_string());
+    }
+    Ok(())
+}
+
 pub fn create_project(
     project_tracker_data: &ProjectTrackerDb,
     project_name: &str,
 ) -> Result<bool, String> {
-    match project_tracker_data.create_project(project_name, 0.0) {
-        Ok(_) => Ok(true),
+    check_project_name(project_name)?;
+    match project_tracker_data.create_project(project_name) {
+        Ok(_) => {
+            hooks::fire(
+                project_tracker_data,
+                Hook::ProjectCreated,
+                vec![("project", Value::Str(normalize_project_name(project_name)))],
+            );
+            Ok(true)
+        }
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            let archived = matches!(
+                project_tracker_data.get_single_project(project_name),
+                Ok(Some(project)) if project.archived_getter()
+            );
+            if archived {
+                Err(format!(
+                    "Project '{}' is archived. Restore it instead of creating it again.",
+                    normalize_project_name(project_name)
+                ))
+            } else {
+                Err(format!(
+                    "Project '{}' already exists.",
+                    normalize_project_name(project_name)
+                ))
+            }
+        }
         Err(e) => Err(format!("Error creating project: {}", e)),
     }
 }
 
+pub fn rename_project(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+    new_name: &str,
+) -> Result<bool, String> {
+    check_project_name(new_name)?;
+    get_project(project_tracker_data, project_name)?;
+    match project_tracker_data.rename_project(project_name, new_name) {
+        Ok(true) => Ok(true),
+        Ok(false) => Err(format!("Project '{}' not found.", project_name)),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err(format!(
+                "Project '{}' already exists.",
+                normalize_project_name(new_name)
+            ))
+        }
+        Err(e) => Err(format!("Error renaming project: {}", e)),
+    }
+}
+
+pub fn get_project(
+    project_tracker_data: &ProjectTrackerDb,
+    project_name: &str,
+) -> Result<ProjectTracker, String> {
+    match project_tracker_data.get_single_project(project_name) {
+        // only restore and purge reach archived projects, and they look them up themselves
+        Ok(Some(project)) if project.archived_getter() => Err(format!(
+            "Project '{}' is archived. Restore it first.",
+            project.name_getter()
+        )),
+        Ok(Some(valid_project)) => Ok(valid_project),
+        Ok(None) => Err(format!("No such project named {}", project_name)),
+        Err(e) => Err(format!("Error retrieving project: {}", e)),
+    }
+}
+
 pub fn get_all_project(
     project_tracker_data: &ProjectTrackerDb,
 ) -> Result<Vec<ProjectTracker>, String> {
@@ -53,19 +521,24 @@ pub fn get_all_project(
     }
 }
 
+pub fn get_archived_projects(
+    project_tracker_data: &ProjectTrackerDb,
+) -> Result<Vec<ProjectTracker>, String> {
+    match project_tracker_data.get_archived_projects() {
+        Ok(result) => Ok(result),
+        Err(e) => Err(format!("Error retrieving archived projects: {}", e)),
+    }
+}
+
 pub fn write_journal(
     project_tracker_data: &ProjectTrackerDb,
     project_name: &str,
 ) -> Result<bool, String> {
-    let project = match project_tracker_data.get_single_project(project_name) {
-        Ok(Some(valid_project)) => valid_project,
-        Ok(None) => return Err(format!("No such project named {}", project_name)),
-        Err(e) => return Err(format!("Error retrieving project: {}", e)),
-    };
+    let project = get_project(project_tracker_data, project_name)?;
     let journal_path = project.path_getter();
     if !journal_path.exists() {
         // Recreate the journal file if it was deleted
-        std::fs::File::create(&journal_path)
+        std::fs::File::create(journal_path)
             .map_err(|e| format!("Failed to recreate journal file: {}", e))?;
     }
     // Open the journal file in the user's default editor (cross-platform)
@@ -75,13 +548,13 @@ pub fn write_journal(
         std::env::var("EDITOR").unwrap_or_else(|_| "nano".to_string())
     };
     let status = std::process::Command::new(editor)
-        .arg(&journal_path)
+        .arg(journal_path)
         .status()
         .map_err(|e| format!("Failed to open editor: {}", e))?;
 
     if status.success() {
         Ok(true)
     } else {
-        Err(format!("Editor exited with a non-zero status."))
+        Err("Editor exited with a non-zero status.".to_string())
     }
 }
@@ -0,0 +1,894 @@
+// the focus/rest cycle as a state machine, free of terminal and database code:
+// a front end feeds it Inputs and the passage of time through a Clock, and
+// acts on the Events it hands back (print, record, checkpoint, notify)
+use crate::tracker::{ActiveSession, Phase, SessionEntry, SessionOutcome, TimerSettings};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// a gap this long between two updates is taken for a suspend or clock change
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(10);
+// a Flowtime focus that nobody stops ends on its own after this long
+const FLOW_LIMIT_SECONDS: u64 = 12 * 60 * 60;
+// how much longer a break gets when it is snoozed
+const SNOOZE_SECONDS: i64 = 5 * 60;
+
+// how long the break after a Flowtime focus should be
+#[derive(Debug, Clone, PartialEq)]
+pub enum BreakRule {
+    // this fraction of the focus time
+    Ratio(f64),
+    // (focus up to, break) pairs in seconds, shortest focus first; longer
+    // focus than the last row gets the last row's break
+    Table(Vec<(u64, u64)>),
+}
+impl Default for BreakRule {
+    // one minute of rest for every five of focus
+    fn default() -> Self {
+        BreakRule::Ratio(0.2)
+    }
+}
+impl BreakRule {
+    // rounded up to whole minutes, and never under one
+    pub fn break_seconds(&self, focus_seconds: u64) -> u64 {
+        let seconds = match self {
+            BreakRule::Ratio(ratio) => (focus_seconds as f64 * ratio).round() as u64,
+            BreakRule::Table(rows) => rows
+                .iter()
+                .find(|(up_to, _)| focus_seconds <= *up_to)
+                .or(rows.last())
+                .map(|(_, rest)| *rest)
+                .unwrap_or(0),
+        };
+        seconds.div_ceil(60).max(1) * 60
+    }
+}
+
+pub trait Clock {
+    // monotonic time, which measures the timers
+    fn now(&self) -> Instant;
+    // wall-clock time, which stamps the recorded sessions and reveals suspends
+    fn wall(&self) -> SystemTime;
+}
+
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+    fn wall(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    TogglePause,
+    Skip,
+    Quit,
+    // the answer to Event::ClockJumped: should the gap count toward the timer?
+    CountSuspended(bool),
+    // add (or, when negative, take off) seconds on the running countdown;
+    // taking off more than is left ends it now, completed
+    Adjust(i64),
+    // a few more minutes of break
+    Snooze,
+    // end the focus now and record it as completed
+    FinishNow,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    PhaseStarted { phase: Phase, cycle: u64 },
+    Paused,
+    Resumed,
+    // the clock leapt ahead by `gap`; the timer holds still until an
+    // Input::CountSuspended answer arrives
+    ClockJumped { gap: Duration },
+    // the running countdown was lengthened or shortened by `change` seconds
+    Adjusted { change: i64, snoozed: bool },
+    // a timer is over, completed, skipped or quit; untouched timers report zero seconds
+    PhaseEnded(SessionEntry),
+    // the session was quit, or ran its target number of cycles; updates do
+    // nothing from here on
+    Ended { target_reached: bool },
+}
+
+// what was done to the timers of a Session, for the summary at its end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimerChanges {
+    pub extended_seconds: u64,
+    pub shortened_seconds: u64,
+    pub snoozes: u64,
+    pub finished_early: u64,
+}
+impl TimerChanges {
+    pub fn is_empty(&self) -> bool {
+        *self == TimerChanges::default()
+    }
+}
+
+struct Timer {
+    run_length: Duration,
+    already_elapsed: u64,
+    deadline: Instant,
+    paused_since: Option<Instant>,
+}
+
+struct ClockJump {
+    gap: Duration,
+    instant_step: Duration,
+    noticed_at: Instant,
+}
+
+pub struct Session<C: Clock> {
+    clock: C,
+    state: ActiveSession,
+    break_rule: BreakRule,
+    // None until the current phase has started
+    timer: Option<Timer>,
+    resuming: bool,
+    jump: Option<ClockJump>,
+    last_instant: Instant,
+    last_wall: SystemTime,
+    ended: bool,
+    focus_seconds: u64,
+    changes: TimerChanges,
+}
+
+impl<C: Clock> Session<C> {
+    pub fn new(clock: C, project_name: &str, settings: &TimerSettings) -> Self {
+        let started_at = unix_seconds(clock.wall());
+        let state = ActiveSession {
+            project_name: project_name.to_string(),
+            phase: Phase::Focus,
+            started_at,
+            elapsed_seconds: 0,
+            paused: false,
+            pause_count: 0,
+            cycle: 1,
+            rest_count: 0,
+            settings: *settings,
+            adjustment_seconds: 0,
+            updated_at: started_at,
+        };
+        Self::from_state(clock, state, false)
+    }
+    // picks up an interrupted timer where its last checkpoint left it
+    pub fn resume(clock: C, active: ActiveSession) -> Self {
+        Self::from_state(clock, active, true)
+    }
+    // sets the rule for the breaks of a Flowtime session
+    pub fn with_break_rule(mut self, break_rule: BreakRule) -> Self {
+        self.break_rule = break_rule;
+        self
+    }
+    fn from_state(clock: C, state: ActiveSession, resuming: bool) -> Self {
+        let (last_instant, last_wall) = (clock.now(), clock.wall());
+        Session {
+            clock,
+            state,
+            break_rule: BreakRule::default(),
+            timer: None,
+            resuming,
+            jump: None,
+            last_instant,
+            last_wall,
+            ended: false,
+            focus_seconds: 0,
+            changes: TimerChanges::default(),
+        }
+    }
+
+    // advance to the clock's current time, then apply `input`; errs, and
+    // ends the session, when a timer is too long to run
+    pub fn update(&mut self, input: Option<Input>) -> Result<Vec<Event>, String> {
+        let mut events = Vec::new();
+        if self.ended {
+            return Ok(events);
+        }
+        let (now, wall) = (self.clock.now(), self.clock.wall());
+        let instant_step = now.saturating_duration_since(self.last_instant);
+        // SystemTime errs when the clock was set back; that is no gap
+        let wall_step = wall.duration_since(self.last_wall).unwrap_or_default();
+        self.last_instant = now;
+        self.last_wall = wall;
+
+        if self.timer.is_none() {
+            self.start_phase(now, wall, &mut events)?;
+        } else if self.jump.is_none() && !self.is_paused() {
+            // updates come every fraction of a second; a far longer step
+            // means the machine slept (the monotonic clock may or may not
+            // have kept counting) or the wall clock was moved
+            let gap = instant_step.max(wall_step);
+            if gap >= SUSPEND_THRESHOLD {
+                self.jump = Some(ClockJump {
+                    gap,
+                    instant_step,
+                    noticed_at: now,
+                });
+                events.push(Event::ClockJumped { gap });
+            }
+        }
+
+        if let Some(input) = input {
+            self.apply(input, now, wall, &mut events)?;
+        }
+        if !self.ended && self.jump.is_none() && self.remaining().is_zero() {
+            self.finish(SessionOutcome::Completed, now, wall, &mut events);
+            if !self.ended {
+                self.start_phase(now, wall, &mut events)?;
+            }
+        }
+        Ok(events)
+    }
+
+    fn apply(
+        &mut self,
+        input: Input,
+        now: Instant,
+        wall: SystemTime,
+        events: &mut Vec<Event>,
+    ) -> Result<(), String> {
+        match input {
+            Input::TogglePause => {
+                if self.jump.is_some() {
+                    return Ok(());
+                }
+                let Some(timer) = self.timer.as_mut() else {
+                    return Ok(());
+                };
+                match timer.paused_since.take() {
+                    Some(since) => {
+                        timer.deadline += now.saturating_duration_since(since);
+                        events.push(Event::Resumed);
+                    }
+                    None => {
+                        timer.paused_since = Some(now);
+                        self.state.pause_count += 1;
+                        events.push(Event::Paused);
+                    }
+                }
+            }
+            Input::Skip => {
+                self.jump = None;
+                // stopping a Flowtime focus is how it is meant to end
+                let outcome = if self.is_counting_up() {
+                    SessionOutcome::Completed
+                } else {
+                    SessionOutcome::Skipped
+                };
+                self.finish(outcome, now, wall, events);
+                if !self.ended {
+                    self.start_phase(now, wall, events)?;
+                }
+            }
+            Input::Quit => {
+                self.jump = None;
+                self.finish(SessionOutcome::Quit, now, wall, events);
+            }
+            Input::CountSuspended(counted) => {
+                let (Some(jump), Some(timer)) = (self.jump.take(), self.timer.as_mut()) else {
+                    return Ok(());
+                };
+                // time spent answering is not part of the jump
+                timer.deadline += now.saturating_duration_since(jump.noticed_at);
+                if counted {
+                    let missing = jump.gap.saturating_sub(jump.instant_step);
+                    timer.deadline = timer.deadline.checked_sub(missing).unwrap_or(now);
+                } else {
+                    timer.deadline += jump.instant_step;
+                }
+            }
+            Input::Adjust(change) => {
+                if let Some(change) = self.adjust(change) {
+                    if change > 0 {
+                        self.changes.extended_seconds += change as u64;
+                    } else {
+                        self.changes.shortened_seconds += change.unsigned_abs();
+                    }
+                    events.push(Event::Adjusted {
+                        change,
+                        snoozed: false,
+                    });
+                }
+            }
+            Input::Snooze => {
+                if self.state.phase == Phase::Focus {
+                    return Ok(());
+                }
+                if let Some(change) = self.adjust(SNOOZE_SECONDS) {
+                    self.changes.snoozes += 1;
+                    events.push(Event::Adjusted {
+                        change,
+                        snoozed: true,
+                    });
+                }
+            }
+            Input::FinishNow => {
+                if self.state.phase != Phase::Focus || self.timer.is_none() {
+                    return Ok(());
+                }
+                self.jump = None;
+                if !self.is_counting_up() {
+                    // the focus is recorded as planned for the time it ran
+                    let elapsed = self.elapsed_at(now) as i64;
+                    let cut = self.planned_seconds() as i64 - elapsed;
+                    self.state.adjustment_seconds -= cut;
+                    self.changes.finished_early += 1;
+                }
+                self.finish(SessionOutcome::Completed, now, wall, events);
+                if !self.ended {
+                    self.start_phase(now, wall, events)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // moves the deadline of a countdown by `change` seconds, but never to
+    // before now; returns the change that was made
+    fn adjust(&mut self, change: i64) -> Option<i64> {
+        if self.jump.is_some() || self.is_counting_up() {
+            return None;
+        }
+        let left = self.remaining().as_secs() as i64;
+        let timer = self.timer.as_mut()?;
+        let change = change.max(-left);
+        if change == 0 {
+            return None;
+        }
+        let step = Duration::from_secs(change.unsigned_abs());
+        if change > 0 {
+            timer.deadline = timer.deadline.checked_add(step)?;
+            timer.run_length += step;
+        } else {
+            timer.deadline -= step;
+            timer.run_length = timer.run_length.saturating_sub(step);
+        }
+        self.state.adjustment_seconds += change;
+        Some(change)
+    }
+
+    fn start_phase(
+        &mut self,
+        now: Instant,
+        wall: SystemTime,
+        events: &mut Vec<Event>,
+    ) -> Result<(), String> {
+        if !self.resuming {
+            self.state.started_at = unix_seconds(wall);
+            self.state.elapsed_seconds = 0;
+            self.state.pause_count = 0;
+            self.state.adjustment_seconds = 0;
+        }
+        // a timer checkpointed while paused comes back paused
+        let was_paused = self.resuming && self.state.paused;
+        self.resuming = false;
+        self.state.paused = false;
+        let already_elapsed = self.state.elapsed_seconds.min(self.planned_seconds());
+        let run_length = Duration::from_secs(self.planned_seconds() - already_elapsed);
+        // a length no clock can reach ends the session rather than the program
+        let Some(deadline) = now.checked_add(run_length) else {
+            self.ended = true;
+            return Err(format!(
+                "The {} timer is too long to run ({} seconds).",
+                self.state.phase.as_str(),
+                run_length.as_secs()
+            ));
+        };
+        self.timer = Some(Timer {
+            run_length,
+            already_elapsed,
+            deadline,
+            paused_since: was_paused.then_some(now),
+        });
+        events.push(Event::PhaseStarted {
+            phase: self.state.phase,
+            cycle: self.state.cycle,
+        });
+        Ok(())
+    }
+
+    fn finish(
+        &mut self,
+        outcome: SessionOutcome,
+        now: Instant,
+        wall: SystemTime,
+        events: &mut Vec<Event>,
+    ) {
+        let elapsed_seconds = match outcome {
+            SessionOutcome::Completed if !self.is_counting_up() => self.planned_seconds(),
+            _ => self.elapsed_at(now),
+        };
+        let phase = self.state.phase;
+        events.push(Event::PhaseEnded(SessionEntry {
+            phase,
+            started_at: self.state.started_at,
+            ended_at: unix_seconds(wall),
+            elapsed_seconds,
+            outcome,
+            adjustment_seconds: self.state.adjustment_seconds,
+        }));
+        if phase == Phase::Focus {
+            self.focus_seconds += elapsed_seconds;
+        }
+        self.timer = None;
+
+        if outcome == SessionOutcome::Quit {
+            self.ended = true;
+            events.push(Event::Ended {
+                target_reached: false,
+            });
+            return;
+        }
+
+        let settings = self.state.settings;
+        match phase {
+            // a Flowtime focus ends when you lose flow, so it is never
+            // skipped; its break follows from how long it lasted
+            Phase::Focus if settings.flow => {
+                self.state.settings.rest_seconds = self.break_rule.break_seconds(elapsed_seconds);
+                self.state.phase = Phase::Rest;
+            }
+            Phase::Focus => self.state.phase = self.next_phase(),
+            Phase::Rest | Phase::LongRest => {
+                // a rest that doesn't count leaves the long rest still due
+                let counts = outcome == SessionOutcome::Completed || settings.count_skipped_rests;
+                if counts && phase == Phase::LongRest {
+                    self.state.rest_count = 0;
+                } else if counts {
+                    self.state.rest_count += 1;
+                }
+                if settings
+                    .target_cycles
+                    .is_some_and(|target| self.state.cycle >= target)
+                {
+                    self.ended = true;
+                    events.push(Event::Ended {
+                        target_reached: true,
+                    });
+                    return;
+                }
+                self.state.phase = Phase::Focus;
+                self.state.cycle += 1;
+            }
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.state.phase
+    }
+    pub fn project_name(&self) -> &str {
+        &self.state.project_name
+    }
+    // the running timer, and the ones after it, are recorded on another project
+    pub fn switch_project(&mut self, project_name: &str) {
+        self.state.project_name = project_name.to_string();
+    }
+    pub fn cycle(&self) -> u64 {
+        self.state.cycle
+    }
+    // the phase that follows the current one, unless the session ends first
+    pub fn next_phase(&self) -> Phase {
+        let settings = &self.state.settings;
+        match self.state.phase {
+            Phase::Focus if settings.flow => Phase::Rest,
+            // the long rest takes the place of every Nth rest
+            Phase::Focus if self.state.rest_count + 1 >= settings.long_break_interval => {
+                Phase::LongRest
+            }
+            Phase::Focus => Phase::Rest,
+            Phase::Rest | Phase::LongRest => Phase::Focus,
+        }
+    }
+    // for a Flowtime focus, the point where it stops on its own
+    pub fn planned_seconds(&self) -> u64 {
+        if self.is_counting_up() {
+            FLOW_LIMIT_SECONDS
+        } else {
+            self.state.planned_seconds()
+        }
+    }
+    // a Flowtime focus counts up instead of down
+    pub fn is_counting_up(&self) -> bool {
+        self.state.settings.flow && self.state.phase == Phase::Focus
+    }
+    pub fn is_flow(&self) -> bool {
+        self.state.settings.flow
+    }
+    pub fn is_paused(&self) -> bool {
+        self.timer
+            .as_ref()
+            .is_some_and(|timer| timer.paused_since.is_some())
+    }
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+    // focus time finished (or cut short) since this Session was created
+    pub fn focus_seconds(&self) -> u64 {
+        self.focus_seconds
+    }
+    pub fn timer_changes(&self) -> TimerChanges {
+        self.changes
+    }
+    // time left on the current timer, frozen while paused or awaiting an answer
+    pub fn remaining(&self) -> Duration {
+        let Some(timer) = self.timer.as_ref() else {
+            return Duration::ZERO;
+        };
+        let at = [
+            timer.paused_since,
+            self.jump.as_ref().map(|jump| jump.noticed_at),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or_else(|| self.clock.now());
+        timer.deadline.saturating_duration_since(at)
+    }
+    // time run on the current timer, frozen like remaining()
+    pub fn elapsed(&self) -> Duration {
+        let Some(timer) = self.timer.as_ref() else {
+            return Duration::ZERO;
+        };
+        Duration::from_secs(timer.already_elapsed)
+            + timer.run_length.saturating_sub(self.remaining())
+    }
+    fn elapsed_at(&self, now: Instant) -> u64 {
+        let Some(timer) = self.timer.as_ref() else {
+            return self.state.elapsed_seconds;
+        };
+        let hold = timer.paused_since.unwrap_or(now);
+        let left = timer.deadline.saturating_duration_since(hold);
+        timer.already_elapsed
+            + (timer.run_length.saturating_sub(left))
+                .as_secs_f64()
+                .round() as u64
+    }
+    // the state as it should be checkpointed right now
+    pub fn snapshot(&self) -> ActiveSession {
+        let mut active = self.state.clone();
+        active.elapsed_seconds = self.elapsed_at(self.clock.now());
+        active.paused = self.is_paused();
+        active.updated_at = unix_seconds(self.clock.wall());
+        active
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // a clock the test moves by hand; clones share the same time
+    #[derive(Clone)]
+    struct ManualClock {
+        now: Rc<Cell<Instant>>,
+        wall: Rc<Cell<SystemTime>>,
+    }
+    impl ManualClock {
+        fn new() -> Self {
+            ManualClock {
+                now: Rc::new(Cell::new(Instant::now())),
+                wall: Rc::new(Cell::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000))),
+            }
+        }
+        fn advance(&self, seconds: u64) {
+            self.now.set(self.now.get() + Duration::from_secs(seconds));
+            self.advance_wall(seconds);
+        }
+        // a suspend the monotonic clock slept through
+        fn advance_wall(&self, seconds: u64) {
+            self.wall
+                .set(self.wall.get() + Duration::from_secs(seconds));
+        }
+    }
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            self.now.get()
+        }
+        fn wall(&self) -> SystemTime {
+            self.wall.get()
+        }
+    }
+
+    fn settings() -> TimerSettings {
+        TimerSettings {
+            focus_seconds: 60,
+            rest_seconds: 30,
+            long_rest_seconds: 90,
+            long_break_interval: 4,
+            target_cycles: None,
+            count_skipped_rests: false,
+            flow: false,
+        }
+    }
+
+    fn start(settings: &TimerSettings) -> (ManualClock, Session<ManualClock>) {
+        let clock = ManualClock::new();
+        let mut session = Session::new(clock.clone(), "writing", settings);
+        let events = session.update(None).unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [Event::PhaseStarted {
+                phase: Phase::Focus,
+                cycle: 1
+            }]
+        ));
+        (clock, session)
+    }
+
+    // moves time on a second per update, as a front end would
+    fn run(clock: &ManualClock, session: &mut Session<ManualClock>, seconds: u64) -> Vec<Event> {
+        let mut events = Vec::new();
+        for _ in 0..seconds {
+            clock.advance(1);
+            events.extend(session.update(None).unwrap());
+        }
+        events
+    }
+
+    fn ended(events: &[Event]) -> Vec<&SessionEntry> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::PhaseEnded(entry) => Some(entry),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_pause_holds_the_countdown_until_resumed() {
+        let (clock, mut session) = start(&settings());
+        run(&clock, &mut session, 20);
+        let events = session.update(Some(Input::TogglePause)).unwrap();
+        assert!(matches!(events.as_slice(), [Event::Paused]));
+        assert!(session.is_paused());
+
+        // a long pause is no clock jump
+        assert!(run(&clock, &mut session, 100).is_empty());
+        assert_eq!(session.remaining(), Duration::from_secs(40));
+        assert_eq!(session.snapshot().elapsed_seconds, 20);
+
+        let events = session.update(Some(Input::TogglePause)).unwrap();
+        assert!(matches!(events.as_slice(), [Event::Resumed]));
+        assert!(run(&clock, &mut session, 39).is_empty());
+        let events = run(&clock, &mut session, 1);
+        let entries = ended(&events);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].outcome, SessionOutcome::Completed);
+        assert_eq!(entries[0].elapsed_seconds, 60);
+        assert_eq!(session.phase(), Phase::Rest);
+        assert_eq!(session.focus_seconds(), 60);
+    }
+
+    #[test]
+    fn a_paused_checkpoint_resumes_paused() {
+        let (clock, mut session) = start(&settings());
+        run(&clock, &mut session, 15);
+        session.update(Some(Input::TogglePause)).unwrap();
+        let checkpoint = session.snapshot();
+        assert!(checkpoint.paused);
+
+        let mut resumed = Session::resume(clock.clone(), checkpoint);
+        resumed.update(None).unwrap();
+        assert!(resumed.is_paused());
+        assert_eq!(resumed.remaining(), Duration::from_secs(45));
+    }
+
+    #[test]
+    fn a_skip_records_the_time_run_and_moves_on() {
+        let (clock, mut session) = start(&settings());
+        run(&clock, &mut session, 12);
+        let events = session.update(Some(Input::Skip)).unwrap();
+        let entries = ended(&events);
+        assert_eq!(entries[0].phase, Phase::Focus);
+        assert_eq!(entries[0].outcome, SessionOutcome::Skipped);
+        assert_eq!(entries[0].elapsed_seconds, 12);
+        assert!(matches!(
+            events.last(),
+            Some(Event::PhaseStarted {
+                phase: Phase::Rest,
+                cycle: 1
+            })
+        ));
+        assert_eq!(session.remaining(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn every_fourth_rest_is_a_long_one() {
+        let (clock, mut session) = start(&settings());
+        let mut rests = Vec::new();
+        for _ in 0..8 {
+            session.update(Some(Input::Skip)).unwrap();
+            rests.push(session.phase());
+            // rests only count toward the long one when they run out
+            let rest = session.planned_seconds();
+            run(&clock, &mut session, rest);
+            assert_eq!(session.phase(), Phase::Focus);
+        }
+        use Phase::{LongRest, Rest};
+        assert_eq!(
+            rests,
+            [Rest, Rest, Rest, LongRest, Rest, Rest, Rest, LongRest]
+        );
+        assert_eq!(session.cycle(), 9);
+    }
+
+    #[test]
+    fn skipped_rests_count_toward_the_long_rest_only_when_set_to() {
+        let (_clock, mut session) = start(&settings());
+        let mut rests = Vec::new();
+        for _ in 0..5 {
+            session.update(Some(Input::Skip)).unwrap();
+            rests.push(session.phase());
+            session.update(Some(Input::Skip)).unwrap();
+        }
+        assert_eq!(rests, [Phase::Rest; 5]);
+
+        let counted = TimerSettings {
+            count_skipped_rests: true,
+            ..settings()
+        };
+        let (_clock, mut session) = start(&counted);
+        let mut rests = Vec::new();
+        for _ in 0..4 {
+            session.update(Some(Input::Skip)).unwrap();
+            rests.push(session.phase());
+            session.update(Some(Input::Skip)).unwrap();
+        }
+        use Phase::{LongRest, Rest};
+        assert_eq!(rests, [Rest, Rest, Rest, LongRest]);
+    }
+
+    #[test]
+    fn a_target_number_of_cycles_ends_the_session() {
+        let target = TimerSettings {
+            target_cycles: Some(2),
+            ..settings()
+        };
+        let (clock, mut session) = start(&target);
+        run(&clock, &mut session, 60 + 30 + 60);
+        let events = run(&clock, &mut session, 30);
+        assert!(matches!(
+            events.last(),
+            Some(Event::Ended {
+                target_reached: true
+            })
+        ));
+        assert!(session.is_ended());
+        assert_eq!(session.focus_seconds(), 120);
+    }
+
+    #[test]
+    fn a_flowtime_break_follows_from_the_focus() {
+        let flow = TimerSettings {
+            flow: true,
+            ..settings()
+        };
+        let (clock, mut session) = start(&flow);
+        assert!(session.is_counting_up());
+        run(&clock, &mut session, 25 * 60);
+        assert_eq!(session.elapsed(), Duration::from_secs(25 * 60));
+        let events = session.update(Some(Input::Skip)).unwrap();
+        let entries = ended(&events);
+        assert_eq!(entries[0].outcome, SessionOutcome::Completed);
+        assert_eq!(entries[0].elapsed_seconds, 25 * 60);
+        assert_eq!(session.phase(), Phase::Rest);
+        assert_eq!(session.planned_seconds(), 5 * 60);
+
+        // a focus nobody stops ends on its own
+        let (clock, mut session) = start(&flow);
+        let events = run(&clock, &mut session, FLOW_LIMIT_SECONDS);
+        assert_eq!(ended(&events)[0].elapsed_seconds, FLOW_LIMIT_SECONDS);
+        assert_eq!(session.phase(), Phase::Rest);
+    }
+
+    #[test]
+    fn break_rules_round_up_to_whole_minutes() {
+        let ratio = BreakRule::default();
+        assert_eq!(ratio.break_seconds(25 * 60), 5 * 60);
+        assert_eq!(ratio.break_seconds(26 * 60), 6 * 60);
+        assert_eq!(ratio.break_seconds(10), 60);
+        assert_eq!(ratio.break_seconds(0), 60);
+
+        let table = BreakRule::Table(vec![(25 * 60, 5 * 60), (50 * 60, 8 * 60)]);
+        assert_eq!(table.break_seconds(20 * 60), 5 * 60);
+        assert_eq!(table.break_seconds(25 * 60), 5 * 60);
+        assert_eq!(table.break_seconds(40 * 60), 8 * 60);
+        assert_eq!(table.break_seconds(90 * 60), 8 * 60);
+    }
+
+    #[test]
+    fn a_clock_jump_waits_for_an_answer() {
+        let long = TimerSettings {
+            focus_seconds: 300,
+            ..settings()
+        };
+
+        // the monotonic clock kept counting through the gap
+        let (clock, mut session) = start(&long);
+        run(&clock, &mut session, 5);
+        clock.advance(60);
+        let events = session.update(None).unwrap();
+        assert!(
+            matches!(events.as_slice(), [Event::ClockJumped { gap }] if *gap == Duration::from_secs(60))
+        );
+        // frozen while the question is open, and no pausing meanwhile
+        run(&clock, &mut session, 7);
+        assert_eq!(session.remaining(), Duration::from_secs(235));
+        assert!(session.update(Some(Input::TogglePause)).unwrap().is_empty());
+        session.update(Some(Input::CountSuspended(false))).unwrap();
+        assert_eq!(session.remaining(), Duration::from_secs(295));
+
+        // the monotonic clock slept; the wall clock shows the gap
+        let (clock, mut session) = start(&long);
+        run(&clock, &mut session, 5);
+        clock.advance_wall(60);
+        let events = session.update(None).unwrap();
+        assert!(matches!(events.as_slice(), [Event::ClockJumped { .. }]));
+        session.update(Some(Input::CountSuspended(true))).unwrap();
+        assert_eq!(session.remaining(), Duration::from_secs(235));
+
+        // a gap longer than the timer finishes it once counted
+        let (clock, mut session) = start(&long);
+        clock.advance(600);
+        session.update(None).unwrap();
+        let events = session.update(Some(Input::CountSuspended(true))).unwrap();
+        let entries = ended(&events);
+        assert_eq!(entries[0].outcome, SessionOutcome::Completed);
+        assert_eq!(entries[0].elapsed_seconds, 300);
+        assert_eq!(session.phase(), Phase::Rest);
+    }
+
+    #[test]
+    fn adjustments_move_the_deadline_but_not_before_now() {
+        let (clock, mut session) = start(&settings());
+        run(&clock, &mut session, 10);
+        session.update(Some(Input::Adjust(120))).unwrap();
+        assert_eq!(session.remaining(), Duration::from_secs(170));
+        // snoozing is for breaks
+        assert!(session.update(Some(Input::Snooze)).unwrap().is_empty());
+
+        let events = session.update(Some(Input::Adjust(-1000))).unwrap();
+        assert!(matches!(
+            events.first(),
+            Some(Event::Adjusted { change: -170, .. })
+        ));
+        assert_eq!(ended(&events)[0].elapsed_seconds, 10);
+        assert_eq!(session.phase(), Phase::Rest);
+        let changes = session.timer_changes();
+        assert_eq!(changes.extended_seconds, 120);
+        assert_eq!(changes.shortened_seconds, 170);
+    }
+
+    #[test]
+    fn a_timer_too_long_to_run_ends_the_session() {
+        let endless = TimerSettings {
+            focus_seconds: u64::MAX,
+            ..settings()
+        };
+        let clock = ManualClock::new();
+        let mut session = Session::new(clock.clone(), "writing", &endless);
+        assert!(session.update(None).is_err());
+        assert!(session.is_ended());
+        assert!(session.update(Some(Input::Skip)).unwrap().is_empty());
+
+        // a huge extension is refused rather than overflowing
+        let (_clock, mut session) = start(&settings());
+        assert!(
+            session
+                .update(Some(Input::Adjust(i64::MAX)))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(session.remaining(), Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,329 @@
+// one short line about the running timer, for tmux, shell prompts and status
+// bars: `pomo status --line`. It reads the daemon if one is running, else the
+// checkpoint of an interactive timer, and prints nothing when no timer runs.
+use crate::cli::STALE_AFTER;
+use crate::config;
+use crate::output::{self, Value};
+use crate::pomodoro;
+use crate::tracker::{ActiveSession, Phase, ProjectTrackerDb, unix_now};
+
+pub const DEFAULT_TEMPLATE: &str = "{phase_icon} {project} {time}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineStyle {
+    Plain,
+    // a waybar custom module with "return-type": "json"
+    Waybar,
+    // one block of the i3bar protocol, as i3blocks and i3status-rust take it
+    I3bar,
+}
+
+// the running timer, wherever it runs
+pub struct LiveStatus {
+    pub project: String,
+    pub phase: Phase,
+    pub paused: bool,
+    pub cycle: u64,
+    pub remaining_seconds: u64,
+    pub elapsed_seconds: u64,
+    // a Flowtime focus counts up and has no remaining time
+    pub counting_up: bool,
+}
+impl LiveStatus {
+    // extrapolated from the last checkpoint, which is at most a few seconds old
+    fn from_active(active: &ActiveSession) -> Self {
+        let since_checkpoint = if active.paused {
+            0
+        } else {
+            (unix_now() - active.updated_at).max(0) as u64
+        };
+        let elapsed_seconds = active.elapsed_seconds + since_checkpoint;
+        let counting_up = active.settings.flow && active.phase == Phase::Focus;
+        LiveStatus {
+            project: active.project_name.clone(),
+            phase: active.phase,
+            paused: active.paused,
+            cycle: active.cycle,
+            remaining_seconds: if counting_up {
+                0
+            } else {
+                active.planned_seconds().saturating_sub(elapsed_seconds)
+            },
+            elapsed_seconds,
+            counting_up,
+        }
+    }
+    // the number a timer shows: time left, or time run when counting up
+    fn time_seconds(&self) -> u64 {
+        if self.counting_up {
+            self.elapsed_seconds
+        } else {
+            self.remaining_seconds
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Project,
+    Phase,
+    PhaseIcon,
+    State,
+    Cycle,
+    Remaining,
+    Elapsed,
+    Time,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeFormat {
+    // 07:05; minutes go past 59 rather than adding hours
+    MinutesSeconds,
+    // 1:07:05
+    HoursMinutesSeconds,
+    // whole minutes, rounded up
+    Minutes,
+    Seconds,
+}
+
+enum Piece {
+    Text(String),
+    Field(Field, TimeFormat),
+}
+
+// "{name}" or "{name:format}" placeholders; "{{" and "}}" are literal braces
+fn parse_template(template: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(format!("unclosed '{{{}'", placeholder)),
+                    }
+                }
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(parse_placeholder(&placeholder)?);
+            }
+            '}' => return Err("a lone '}'; write '}}' for a brace".to_string()),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Piece, String> {
+    let (name, format) = match placeholder.split_once(':') {
+        Some((name, format)) => (name, Some(format)),
+        None => (placeholder, None),
+    };
+    let field = match name {
+        "project" => Field::Project,
+        "phase" => Field::Phase,
+        "phase_icon" => Field::PhaseIcon,
+        "state" => Field::State,
+        "cycle" => Field::Cycle,
+        "remaining" => Field::Remaining,
+        "elapsed" => Field::Elapsed,
+        "time" => Field::Time,
+        _ => return Err(format!("unknown placeholder '{{{}}}'", placeholder)),
+    };
+    let is_time = matches!(field, Field::Remaining | Field::Elapsed | Field::Time);
+    let time_format = match format {
+        None | Some("mm:ss") => TimeFormat::MinutesSeconds,
+        Some("hh:mm:ss") => TimeFormat::HoursMinutesSeconds,
+        Some("m") => TimeFormat::Minutes,
+        Some("s") => TimeFormat::Seconds,
+        Some(other) => {
+            return Err(format!(
+                "unknown time format '{}' in '{{{}}}'; use mm:ss, hh:mm:ss, m or s",
+                other, placeholder
+            ));
+        }
+    };
+    if format.is_some() && !is_time {
+        return Err(format!("'{{{}}}' takes no format", name));
+    }
+    Ok(Piece::Field(field, time_format))
+}
+
+// `template` filled in for a timer that isn't necessarily the live one
+pub fn render_status(template: &str, status: &LiveStatus) -> Result<String, String> {
+    Ok(render_template(&parse_template(template)?, status))
+}
+
+// checks a template for the status_line_format setting
+pub fn check_template(template: &str) -> Result<(), String> {
+    parse_template(template).map(|_| ())
+}
+
+fn format_time(seconds: u64, format: TimeFormat) -> String {
+    match format {
+        TimeFormat::MinutesSeconds => format!("{:02}:{:02}", seconds / 60, seconds % 60),
+        TimeFormat::HoursMinutesSeconds => format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        ),
+        TimeFormat::Minutes => seconds.div_ceil(60).to_string(),
+        TimeFormat::Seconds => seconds.to_string(),
+    }
+}
+
+fn phase_name(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Focus => "Focus",
+        Phase::Rest => "Rest",
+        Phase::LongRest => "Long rest",
+    }
+}
+
+fn phase_icon(status: &LiveStatus) -> &'static str {
+    if status.paused {
+        return "⏸";
+    }
+    match status.phase {
+        Phase::Focus => "🍅",
+        Phase::Rest => "☕",
+        Phase::LongRest => "🌴",
+    }
+}
+
+fn render_template(pieces: &[Piece], status: &LiveStatus) -> String {
+    let mut line = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => line.push_str(text),
+            Piece::Field(field, time_format) => line.push_str(&match field {
+                Field::Project => status.project.clone(),
+                Field::Phase => phase_name(status.phase).to_string(),
+                Field::PhaseIcon => phase_icon(status).to_string(),
+                Field::State => if status.paused { "paused" } else { "running" }.to_string(),
+                Field::Cycle => status.cycle.to_string(),
+                Field::Remaining => format_time(status.remaining_seconds, *time_format),
+                Field::Elapsed => format_time(status.elapsed_seconds, *time_format),
+                Field::Time => format_time(status.time_seconds(), *time_format),
+            }),
+        }
+    }
+    line
+}
+
+// the timer the daemon runs, or else one running in an interactive pomo
+pub fn live_status(project_tracker_data: &ProjectTrackerDb) -> Result<Option<LiveStatus>, String> {
+    #[cfg(unix)]
+    if let Some(status) = daemon_status(project_tracker_data) {
+        return Ok(Some(status));
+    }
+    let Some(active) = pomodoro::get_active_session(project_tracker_data)? else {
+        return Ok(None);
+    };
+    // the pomo that wrote a stale checkpoint is gone
+    if unix_now() - active.updated_at >= STALE_AFTER.as_secs() as i64 {
+        return Ok(None);
+    }
+    Ok(Some(LiveStatus::from_active(&active)))
+}
+
+// None when no daemon answers or it has no timer
+#[cfg(unix)]
+fn daemon_status(project_tracker_data: &ProjectTrackerDb) -> Option<LiveStatus> {
+    use crate::args::Control;
+    use crate::daemon;
+
+    let reply = daemon::request(project_tracker_data, &Control::Status).ok()?;
+    let number = |name: &str| reply.field(name)?.parse::<u64>().ok();
+    let state = reply.field("state")?;
+    if state == "idle" {
+        return None;
+    }
+    let phase = Phase::parse(reply.field("phase")?)?;
+    let counting_up = reply.field("flow") == Some("true") && phase == Phase::Focus;
+    Some(LiveStatus {
+        project: reply.field("project")?.to_string(),
+        phase,
+        paused: state == "paused",
+        cycle: number("cycle")?,
+        remaining_seconds: if counting_up {
+            0
+        } else {
+            number("remaining_seconds")?
+        },
+        elapsed_seconds: number("elapsed_seconds")?,
+        counting_up,
+    })
+}
+
+// the line to print, or None when no timer runs; `template` falls back to
+// the status_line_format setting, then to DEFAULT_TEMPLATE
+pub fn render(
+    project_tracker_data: &ProjectTrackerDb,
+    style: LineStyle,
+    template: Option<&str>,
+) -> Result<Option<String>, String> {
+    let template = match template {
+        Some(template) => template.to_string(),
+        None => config::get(project_tracker_data, "status_line_format")?
+            .unwrap_or(DEFAULT_TEMPLATE.to_string()),
+    };
+    let pieces =
+        parse_template(&template).map_err(|e| format!("Invalid status line format: {}", e))?;
+    let Some(status) = live_status(project_tracker_data)? else {
+        return Ok(None);
+    };
+    let text = render_template(&pieces, &status);
+    let line = match style {
+        LineStyle::Plain => text,
+        LineStyle::Waybar => {
+            let tooltip = format!(
+                "{} on '{}', cycle {}{}",
+                phase_name(status.phase),
+                status.project,
+                status.cycle,
+                if status.paused { " (paused)" } else { "" }
+            );
+            let class = if status.paused {
+                "paused"
+            } else {
+                status.phase.as_str()
+            };
+            output::json_object(&vec![
+                ("text", Value::Str(text)),
+                ("tooltip", Value::Str(tooltip)),
+                ("class", Value::Str(class.to_string())),
+                ("alt", Value::Str(status.phase.as_str().to_string())),
+            ])
+        }
+        LineStyle::I3bar => output::json_object(&vec![
+            ("name", Value::Str("pomo".to_string())),
+            ("full_text", Value::Str(text)),
+            (
+                "short_text",
+                Value::Str(format_time(
+                    status.time_seconds(),
+                    TimeFormat::MinutesSeconds,
+                )),
+            ),
+        ]),
+    };
+    Ok(Some(line))
+}
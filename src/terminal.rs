@@ -0,0 +1,189 @@
+// the terminal as a notifier: the window or tab title follows the running
+// timer (OSC 0), and a finished timer pops up a notification through the
+// terminal itself (OSC 9, OSC 777 or OSC 99), so a timer in a background tab
+// is seen and heard without any desktop notification service.
+use crate::config;
+use crate::session::{Clock, Event, Session};
+use crate::statusline::{self, LiveStatus};
+use crate::tracker::{Phase, ProjectTrackerDb, SessionOutcome};
+use std::io::{self, IsTerminal, Write};
+
+const TITLE_TEMPLATE: &str = "{phase_icon} {time} {phase} · {project}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    // iTerm2, WezTerm, Ghostty, ConEmu: body only
+    Osc9,
+    // foot, urxvt, WezTerm, Ghostty: title and body
+    Osc777,
+    // kitty's own
+    Osc99,
+}
+impl Protocol {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "osc9" => Some(Protocol::Osc9),
+            "osc777" => Some(Protocol::Osc777),
+            "osc99" => Some(Protocol::Osc99),
+            _ => None,
+        }
+    }
+    // what the terminal we run in is known to show, judged by the variables
+    // it sets; these survive into tmux and ssh -t sessions more often than TERM
+    pub fn detect() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let set = |name: &str| std::env::var_os(name).is_some();
+        let term_program = var("TERM_PROGRAM");
+        let term = var("TERM");
+        if set("KITTY_WINDOW_ID") || term == "xterm-kitty" {
+            Some(Protocol::Osc99)
+        } else if term.starts_with("foot") {
+            Some(Protocol::Osc777)
+        } else if set("WEZTERM_PANE")
+            || set("ITERM_SESSION_ID")
+            || set("GHOSTTY_RESOURCES_DIR")
+            || matches!(term_program.as_str(), "WezTerm" | "iTerm.app" | "ghostty")
+        {
+            Some(Protocol::Osc9)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Terminal {
+    // nothing is written unless stdout is a terminal
+    enabled: bool,
+    show_title: bool,
+    protocol: Option<Protocol>,
+    // whether the protocol was picked by hand rather than detected
+    chosen: bool,
+    // inside tmux, notifications must be passed through to the outer terminal
+    tmux: bool,
+    last_title: Option<String>,
+}
+
+impl Terminal {
+    pub fn new(project_tracker_data: &ProjectTrackerDb) -> Self {
+        let setting = |key: &str| config::get(project_tracker_data, key).ok().flatten();
+        let notifications = setting("terminal_notifications").unwrap_or("auto".to_string());
+        let (protocol, chosen) = match notifications.as_str() {
+            "off" => (None, true),
+            "auto" => (Protocol::detect(), false),
+            other => (Protocol::parse(other), true),
+        };
+        Terminal {
+            enabled: io::stdout().is_terminal(),
+            show_title: setting("terminal_title").is_none_or(|value| value == "true"),
+            protocol,
+            chosen,
+            tmux: std::env::var_os("TMUX").is_some(),
+            last_title: None,
+        }
+    }
+
+    // sets the title to the timer's state; only writes when it changes
+    pub fn update_title<C: Clock>(&mut self, session: &Session<C>) {
+        if !self.enabled || !self.show_title {
+            return;
+        }
+        let counting_up = session.is_counting_up();
+        let status = LiveStatus {
+            project: session.project_name().to_string(),
+            phase: session.phase(),
+            paused: session.is_paused(),
+            cycle: session.cycle(),
+            // rounded up, as the countdown shows it
+            remaining_seconds: session.remaining().as_millis().div_ceil(1000) as u64,
+            elapsed_seconds: session.elapsed().as_secs(),
+            counting_up,
+        };
+        let Ok(title) = statusline::render_status(TITLE_TEMPLATE, &status) else {
+            return;
+        };
+        if self.last_title.as_ref() == Some(&title) {
+            return;
+        }
+        // the first title of the session saves the old one to put it back later
+        let save = if self.last_title.is_none() {
+            "\x1B[22;0t"
+        } else {
+            ""
+        };
+        self.write(&format!("{}\x1B]0;{}\x07", save, clean(&title)));
+        self.last_title = Some(title);
+    }
+
+    // a notification for a timer that ran out; a detected terminal stays
+    // quiet when a desktop notification already says the same
+    pub fn on_event<C: Clock>(&mut self, session: &Session<C>, event: &Event, desktop_shown: bool) {
+        let Event::PhaseEnded(entry) = event else {
+            return;
+        };
+        if entry.outcome != SessionOutcome::Completed || (desktop_shown && !self.chosen) {
+            return;
+        }
+        let done = match entry.phase {
+            Phase::Focus => "Focus done",
+            Phase::Rest => "Rest over",
+            Phase::LongRest => "Long rest over",
+        };
+        let next = if session.is_ended() {
+            "all cycles done".to_string()
+        } else {
+            match session.phase() {
+                Phase::Focus => "back to focus".to_string(),
+                Phase::Rest => "time for a rest".to_string(),
+                Phase::LongRest => "time for a long rest".to_string(),
+            }
+        };
+        self.notify(done, &format!("{} · {}", session.project_name(), next));
+    }
+
+    fn notify(&mut self, title: &str, body: &str) {
+        let Some(protocol) = self.protocol else {
+            return;
+        };
+        if !self.enabled {
+            return;
+        }
+        let (title, body) = (clean(title), clean(body));
+        let sequence = match protocol {
+            Protocol::Osc9 => format!("\x1B]9;{}: {}\x07", title, body),
+            // ';' splits the fields, so the title can't hold one
+            Protocol::Osc777 => format!("\x1B]777;notify;{};{}\x07", title.replace(';', ","), body),
+            // title and body as two chunks of one notification
+            Protocol::Osc99 => format!(
+                "\x1B]99;i=pomo:d=0;{}\x1B\\\x1B]99;i=pomo:d=1:p=body;{}\x1B\\",
+                title, body
+            ),
+        };
+        let sequence = if self.tmux {
+            // tmux hands on what is wrapped like this, with allow-passthrough on
+            format!("\x1BPtmux;{}\x1B\\", sequence.replace('\x1B', "\x1B\x1B"))
+        } else {
+            sequence
+        };
+        self.write(&sequence);
+    }
+
+    fn write(&self, sequence: &str) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(sequence.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+impl Drop for Terminal {
+    // puts back the title the terminal had before the session
+    fn drop(&mut self) {
+        if self.last_title.is_some() {
+            self.write("\x1B[23;0t");
+        }
+    }
+}
+
+// control characters would end the sequence early
+fn clean(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
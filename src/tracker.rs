@@ -1,5 +1,8 @@
+use crate::migrations;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
 use rusqlite::{Connection, Result, params};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 fn sanitize_filename(name: &str) -> String {
     // Remove invalid characters and control chars, trim trailing spaces/dots (invalid on Windows)
     let forbidden = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
@@ -22,25 +25,241 @@ fn sanitize_filename(name: &str) -> String {
     }
     if s.is_empty() { "_".into() } else { s }
 }
-fn get_project_path(project_name: &str) -> PathBuf {
-    // Use proper platform-specific data directory
-    let mut p_path = dirs::data_dir().expect("Could not find data directory");
-    p_path.push("pomo");
-    p_path.push("journal");
-    std::fs::create_dir_all(&p_path).expect("Failed to create data directory");
-    let project_journal_name = format!("{}.md", sanitize_filename(project_name));
-    p_path.push(project_journal_name);
-    p_path
+// collapse runs of whitespace so "my  project " and "my project" are the same name
+pub fn normalize_project_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+// the case-insensitive key project names are unique on
+pub fn project_key(name: &str) -> String {
+    normalize_project_name(name).to_lowercase()
+}
+// journals are stored relative to the journal directory so a data
+// directory can be moved or carried around as a whole
+fn journal_file_name(project_name: &str) -> String {
+    format!("{}.md", sanitize_filename(project_name))
+}
+
+// surface a failure that did not come from SQLite through rusqlite's error type
+pub fn db_error(message: String) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+        Some(message),
+    )
+}
+
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Focus,
+    Rest,
+    LongRest,
+}
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Focus => "focus",
+            Phase::Rest => "rest",
+            Phase::LongRest => "long_rest",
+        }
+    }
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "focus" => Some(Phase::Focus),
+            "rest" => Some(Phase::Rest),
+            "long_rest" => Some(Phase::LongRest),
+            _ => None,
+        }
+    }
+}
+impl FromSql for Phase {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Phase::parse(value.as_str()?).ok_or(FromSqlError::InvalidType)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionOutcome {
+    Completed,
+    Skipped,
+    Quit,
+}
+impl SessionOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionOutcome::Completed => "completed",
+            SessionOutcome::Skipped => "skipped",
+            SessionOutcome::Quit => "quit",
+        }
+    }
+}
+impl FromSql for SessionOutcome {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "completed" => Ok(SessionOutcome::Completed),
+            "skipped" => Ok(SessionOutcome::Skipped),
+            "quit" => Ok(SessionOutcome::Quit),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+// one timer run as it is written to the sessions table
+#[derive(Debug, Clone)]
+pub struct SessionEntry {
+    pub phase: Phase,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub elapsed_seconds: u64,
+    pub outcome: SessionOutcome,
+    // minutes added to or taken off the planned length while it ran, in seconds
+    pub adjustment_seconds: i64,
+}
+
+// the longest length a timer (or a block of logged time) may be given, in minutes
+pub const MAX_MINUTES: f32 = 24.0 * 60.0;
+
+// lengths of the three timer phases, in seconds, and the rules of the cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerSettings {
+    pub focus_seconds: u64,
+    pub rest_seconds: u64,
+    pub long_rest_seconds: u64,
+    // pomodoros per long rest: the long rest takes the place of every Nth rest
+    pub long_break_interval: u64,
+    // end the session on its own after this many focus/rest cycles
+    pub target_cycles: Option<u64>,
+    pub count_skipped_rests: bool,
+    // Flowtime: focus counts up until stopped, and the rest that follows is
+    // worked out from it (rest_seconds then holds the last suggestion)
+    pub flow: bool,
+}
+impl Default for TimerSettings {
+    // the classic 25/5/15 pomodoro with a long rest every 4th break
+    fn default() -> Self {
+        TimerSettings {
+            focus_seconds: 25 * 60,
+            rest_seconds: 5 * 60,
+            long_rest_seconds: 15 * 60,
+            long_break_interval: 4,
+            target_cycles: None,
+            count_skipped_rests: false,
+            flow: false,
+        }
+    }
+}
+
+// a named set of timer lengths, in seconds
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub name: String,
+    pub focus_seconds: u64,
+    pub rest_seconds: u64,
+    pub long_rest_seconds: u64,
+}
+impl Preset {
+    fn from_row(row: &rusqlite::Row) -> Result<Self> {
+        Ok(Preset {
+            name: row.get(0)?,
+            focus_seconds: row.get(1)?,
+            rest_seconds: row.get(2)?,
+            long_rest_seconds: row.get(3)?,
+        })
+    }
+}
+
+// the timer rhythm saved on a project, overriding the global defaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProjectTimerDefaults {
+    pub focus_seconds: u64,
+    pub rest_seconds: u64,
+    pub long_rest_seconds: u64,
+    pub long_break_interval: u64,
+}
+impl ProjectTimerDefaults {
+    pub fn from_settings(settings: &TimerSettings) -> Self {
+        ProjectTimerDefaults {
+            focus_seconds: settings.focus_seconds,
+            rest_seconds: settings.rest_seconds,
+            long_rest_seconds: settings.long_rest_seconds,
+            long_break_interval: settings.long_break_interval,
+        }
+    }
+    pub fn apply(&self, settings: &mut TimerSettings) {
+        settings.focus_seconds = self.focus_seconds;
+        settings.rest_seconds = self.rest_seconds;
+        settings.long_rest_seconds = self.long_rest_seconds;
+        settings.long_break_interval = self.long_break_interval;
+    }
+}
+
+// a stored session together with its row id and owning project
+#[derive(Debug, Clone)]
+pub struct SessionRecord {
+    pub id: i64,
+    pub project_name: String,
+    pub entry: SessionEntry,
+}
+impl SessionRecord {
+    fn from_row(row: &rusqlite::Row) -> Result<Self> {
+        Ok(SessionRecord {
+            id: row.get(0)?,
+            project_name: row.get(1)?,
+            entry: SessionEntry {
+                phase: row.get(2)?,
+                started_at: row.get(3)?,
+                ended_at: row.get(4)?,
+                elapsed_seconds: row.get(5)?,
+                outcome: row.get(6)?,
+                adjustment_seconds: row.get(7)?,
+            },
+        })
+    }
+}
+
+// the timer that is running right now, checkpointed while it runs so a
+// crash or a closed terminal loses at most a few seconds
+#[derive(Debug, Clone)]
+pub struct ActiveSession {
+    pub project_name: String,
+    pub phase: Phase,
+    pub started_at: i64,
+    pub elapsed_seconds: u64,
+    pub paused: bool,
+    pub pause_count: u64,
+    pub cycle: u64,
+    pub rest_count: u64,
+    pub settings: TimerSettings,
+    // the running timer's extensions, snoozes and cuts, in seconds
+    pub adjustment_seconds: i64,
+    pub updated_at: i64,
+}
+impl ActiveSession {
+    // the length the interrupted timer was set to, adjustments included
+    pub fn planned_seconds(&self) -> u64 {
+        let base = match self.phase {
+            Phase::Focus => self.settings.focus_seconds,
+            Phase::Rest => self.settings.rest_seconds,
+            Phase::LongRest => self.settings.long_rest_seconds,
+        };
+        base.saturating_add_signed(self.adjustment_seconds)
+    }
 }
 
 #[derive(Debug)]
 pub struct ProjectTracker {
     project_name: String,
-    time_invested: f32,
+    time_invested: Duration,
     journal_path: PathBuf,
+    archived: bool,
 }
 impl ProjectTracker {
-    pub fn time_getter(&self) -> f32 {
+    pub fn time_getter(&self) -> Duration {
         self.time_invested
     }
     pub fn name_getter(&self) -> &str {
@@ -49,52 +268,95 @@ impl ProjectTracker {
     pub fn path_getter(&self) -> &PathBuf {
         &self.journal_path
     }
+    pub fn archived_getter(&self) -> bool {
+        self.archived
+    }
+    fn from_row(row: &rusqlite::Row, journal_dir: &Path) -> Result<Self> {
+        Ok(ProjectTracker {
+            project_name: row.get(0)?,
+            time_invested: Duration::from_secs(row.get(1)?),
+            journal_path: journal_dir.join(row.get::<_, String>(2)?),
+            archived: row.get(3)?,
+        })
+    }
 }
 
+// time_invested is the sum of all focus sessions, in whole seconds
+const PROJECT_SELECT: &str =
+    "SELECT p.project_name, COALESCE(SUM(s.elapsed_seconds), 0), p.journal_path,
+        p.archived_at IS NOT NULL
+    FROM project_with_journal p
+    LEFT JOIN sessions s ON s.project_id = p.id AND s.phase = 'focus'";
+
+const PRESET_SELECT: &str =
+    "SELECT name, focus_seconds, rest_seconds, long_rest_seconds FROM presets";
+
+const SESSION_SELECT: &str = "SELECT s.id, p.project_name, s.phase, s.started_at, s.ended_at,
+        s.elapsed_seconds, s.outcome, s.adjustment_seconds
+    FROM sessions s
+    JOIN project_with_journal p ON p.id = s.project_id";
+
 pub struct ProjectTrackerDb {
     conn: Connection,
+    data_dir: PathBuf,
+    journal_dir: PathBuf,
 }
 
 impl ProjectTrackerDb {
-    pub fn new(db_path: &str) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
-
-        // Create table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS project_with_journal (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                project_name TEXT NOT NULL,
-                time_invested FLOAT NOT NULL,
-                journal_path TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        Ok(ProjectTrackerDb { conn })
+    // opens (or creates) pomodoro.db and the journal directory inside data_dir
+    pub fn new(data_dir: &Path) -> Result<Self> {
+        let journal_dir = data_dir.join("journal");
+        std::fs::create_dir_all(&journal_dir).map_err(|e| {
+            db_error(format!(
+                "Failed to create data directory '{}': {}",
+                journal_dir.display(),
+                e
+            ))
+        })?;
+        let db_path = data_dir.join("pomodoro.db");
+        let db_path = db_path.to_string_lossy();
+        let mut conn = Connection::open(db_path.as_ref())?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        migrations::migrate(&mut conn, &db_path)?;
+        Ok(ProjectTrackerDb {
+            conn,
+            data_dir: data_dir.to_path_buf(),
+            journal_dir,
+        })
+    }
+    pub fn data_dir_getter(&self) -> &Path {
+        &self.data_dir
     }
-    pub fn create_project(&self, project_name: &str, time_invested: f32) -> Result<i64> {
-        let journal_path = get_project_path(project_name);
+    // fails with a constraint violation if the name is already taken
+    pub fn create_project(&self, project_name: &str) -> Result<i64> {
+        let project_name = normalize_project_name(project_name);
+        let journal_name = journal_file_name(&project_name);
+        self.conn.execute(
+            "INSERT INTO project_with_journal (project_name, name_key, journal_path) VALUES (?1, ?2, ?3)",
+            params![project_name, project_key(&project_name), journal_name]
+        )?;
         //create the journal file if it does not exist
+        let journal_path = self.journal_dir.join(journal_name);
         if !journal_path.exists() {
-            std::fs::File::create(&journal_path).expect("Failed to create journal file");
+            std::fs::File::create(&journal_path)
+                .map_err(|e| db_error(format!("Failed to create journal file: {}", e)))?;
         }
-        self.conn.execute(
-            "INSERT INTO project_with_journal (project_name, time_invested, journal_path) VALUES (?1, ?2, ?3)", 
-            params![project_name.to_string(), time_invested, journal_path.to_string_lossy().to_string()]
-        )?;
         Ok(self.conn.last_insert_rowid())
     }
+    // active projects only; archived ones are hidden from pickers and status
     pub fn get_projects(&self) -> Result<Vec<ProjectTracker>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT project_name, time_invested, journal_path FROM project_with_journal",
-        )?;
-        let project_iter = stmt.query_map([], |row| {
-            Ok(ProjectTracker {
-                project_name: row.get(0)?,
-                time_invested: row.get(1)?,
-                journal_path: PathBuf::from(row.get::<_, String>(2)?),
-            })
-        })?;
+        self.query_projects("p.archived_at IS NULL")
+    }
+    pub fn get_archived_projects(&self) -> Result<Vec<ProjectTracker>> {
+        self.query_projects("p.archived_at IS NOT NULL")
+    }
+    fn query_projects(&self, filter: &str) -> Result<Vec<ProjectTracker>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{} WHERE {} GROUP BY p.id ORDER BY p.id",
+            PROJECT_SELECT, filter
+        ))?;
+        let project_iter =
+            stmt.query_map([], |row| ProjectTracker::from_row(row, &self.journal_dir))?;
 
         let mut projects = Vec::new();
         for project in project_iter {
@@ -103,44 +365,468 @@ impl ProjectTrackerDb {
         Ok(projects)
     }
     pub fn get_single_project(&self, project_name: &str) -> Result<Option<ProjectTracker>> {
-        let mut stmt = self.conn.prepare("SELECT project_name, time_invested, journal_path FROM project_with_journal WHERE project_name = ?1")?;
-        let mut rows = stmt.query(params![project_name])?;
+        let mut stmt = self.conn.prepare(&format!(
+            "{} WHERE p.name_key = ?1 GROUP BY p.id",
+            PROJECT_SELECT
+        ))?;
+        let mut rows = stmt.query(params![project_key(project_name)])?;
 
         if let Some(row) = rows.next()? {
-            Ok(Some(ProjectTracker {
-                project_name: row.get(0)?,
-                time_invested: row.get(1)?,
-                journal_path: PathBuf::from(row.get::<_, String>(2)?),
-            }))
+            Ok(Some(ProjectTracker::from_row(row, &self.journal_dir)?))
         } else {
             Ok(None)
         }
     }
-    pub fn update_project(&self, project_name: &str, time_invested: f32) -> Result<bool> {
+    pub fn record_session(&self, project_name: &str, entry: &SessionEntry) -> Result<bool> {
+        let inserted = self.conn.execute(
+            "INSERT INTO sessions (project_id, phase, started_at, ended_at, elapsed_seconds, outcome,
+                adjustment_seconds)
+             SELECT id, ?2, ?3, ?4, ?5, ?6, ?7 FROM project_with_journal WHERE name_key = ?1",
+            params![
+                project_key(project_name),
+                entry.phase.as_str(),
+                entry.started_at,
+                entry.ended_at,
+                entry.elapsed_seconds,
+                entry.outcome.as_str(),
+                entry.adjustment_seconds
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+    // newest first
+    pub fn get_project_sessions(
+        &self,
+        project_name: &str,
+        limit: usize,
+    ) -> Result<Vec<SessionRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{} WHERE p.name_key = ?1 ORDER BY s.started_at DESC, s.id DESC LIMIT ?2",
+            SESSION_SELECT
+        ))?;
+        let session_iter = stmt.query_map(
+            params![project_key(project_name), limit as i64],
+            SessionRecord::from_row,
+        )?;
+        session_iter.collect()
+    }
+    // newest first, across all projects
+    pub fn get_recent_sessions(&self, limit: usize) -> Result<Vec<SessionRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{} ORDER BY s.started_at DESC, s.id DESC LIMIT ?1",
+            SESSION_SELECT
+        ))?;
+        let session_iter = stmt.query_map(params![limit as i64], SessionRecord::from_row)?;
+        session_iter.collect()
+    }
+    pub fn get_session(&self, session_id: i64) -> Result<Option<SessionRecord>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{} WHERE s.id = ?1", SESSION_SELECT))?;
+        let mut rows = stmt.query(params![session_id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(SessionRecord::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+    pub fn update_session(&self, session_id: i64, entry: &SessionEntry) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE sessions SET phase = ?1, started_at = ?2, ended_at = ?3, elapsed_seconds = ?4, outcome = ?5
+             WHERE id = ?6",
+            params![
+                entry.phase.as_str(),
+                entry.started_at,
+                entry.ended_at,
+                entry.elapsed_seconds,
+                entry.outcome.as_str(),
+                session_id
+            ],
+        )?;
+        Ok(updated > 0)
+    }
+    pub fn move_session(&self, session_id: i64, project_name: &str) -> Result<bool> {
+        let moved = self.conn.execute(
+            "UPDATE sessions SET project_id = (SELECT id FROM project_with_journal WHERE name_key = ?1)
+             WHERE id = ?2 AND EXISTS (SELECT 1 FROM project_with_journal WHERE name_key = ?1)",
+            params![project_key(project_name), session_id],
+        )?;
+        Ok(moved > 0)
+    }
+    // cut a session into [started_at, started_at + first_seconds) and the rest;
+    // returns the id of the new second half
+    pub fn split_session(&self, session_id: i64, first_seconds: u64) -> Result<Option<i64>> {
+        let Some(session) = self.get_session(session_id)? else {
+            return Ok(None);
+        };
+        let entry = session.entry;
+        let split_at = (entry.started_at + first_seconds as i64).min(entry.ended_at);
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO sessions (project_id, phase, started_at, ended_at, elapsed_seconds, outcome)
+             SELECT project_id, phase, ?1, ?2, ?3, outcome FROM sessions WHERE id = ?4",
+            params![
+                split_at,
+                entry.ended_at,
+                entry.elapsed_seconds - first_seconds,
+                session_id
+            ],
+        )?;
+        let second_id = tx.last_insert_rowid();
+        tx.execute(
+            "UPDATE sessions SET ended_at = ?1, elapsed_seconds = ?2 WHERE id = ?3",
+            params![split_at, first_seconds, session_id],
+        )?;
+        tx.commit()?;
+        Ok(Some(second_id))
+    }
+    // the focus block most recently written, by insertion order. The totals
+    // carried over from before the session history (zero-width rows stamped
+    // with the migration time) are no block of work, and never undone.
+    pub fn get_last_focus_session(&self) -> Result<Option<SessionRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{} WHERE s.phase = 'focus' AND NOT (s.started_at = s.ended_at AND s.elapsed_seconds > 0)
+             ORDER BY s.id DESC LIMIT 1",
+            SESSION_SELECT
+        ))?;
+        let mut rows = stmt.query([])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(SessionRecord::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+    // delete a session and keep a full copy of it in the audit log
+    pub fn undo_session(&self, session: &SessionRecord) -> Result<bool> {
+        let tx = self.conn.unchecked_transaction()?;
+        let deleted = tx.execute("DELETE FROM sessions WHERE id = ?1", params![session.id])?;
+        if deleted == 0 {
+            return Ok(false);
+        }
+        let details = format!(
+            "session={} project={:?} phase={} started_at={} ended_at={} elapsed_seconds={} outcome={}",
+            session.id,
+            session.project_name,
+            session.entry.phase.as_str(),
+            session.entry.started_at,
+            session.entry.ended_at,
+            session.entry.elapsed_seconds,
+            session.entry.outcome.as_str()
+        );
+        tx.execute(
+            "INSERT INTO audit_log (created_at, action, details) VALUES (?1, 'undo_session', ?2)",
+            params![unix_now(), details],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+    // replaces the checkpoint; there is only ever one active session
+    pub fn save_active_session(&self, active: &ActiveSession) -> Result<bool> {
+        let saved = self.conn.execute(
+            "INSERT OR REPLACE INTO active_session (id, project_id, phase, started_at,
+                elapsed_seconds, paused, pause_count, cycle, rest_count, focus_seconds,
+                rest_seconds, long_rest_seconds, updated_at, long_break_interval,
+                target_cycles, count_skipped_rests, flow, adjustment_seconds)
+             SELECT 1, id, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17
+             FROM project_with_journal WHERE name_key = ?1",
+            params![
+                project_key(&active.project_name),
+                active.phase.as_str(),
+                active.started_at,
+                active.elapsed_seconds,
+                active.paused,
+                active.pause_count,
+                active.cycle,
+                active.rest_count,
+                active.settings.focus_seconds,
+                active.settings.rest_seconds,
+                active.settings.long_rest_seconds,
+                active.updated_at,
+                active.settings.long_break_interval,
+                active.settings.target_cycles,
+                active.settings.count_skipped_rests,
+                active.settings.flow,
+                active.adjustment_seconds
+            ],
+        )?;
+        Ok(saved > 0)
+    }
+    pub fn get_active_session(&self) -> Result<Option<ActiveSession>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.project_name, a.phase, a.started_at, a.elapsed_seconds, a.paused,
+                a.pause_count, a.cycle, a.rest_count, a.focus_seconds, a.rest_seconds,
+                a.long_rest_seconds, a.updated_at, a.long_break_interval, a.target_cycles,
+                a.count_skipped_rests, a.flow, a.adjustment_seconds
+             FROM active_session a JOIN project_with_journal p ON p.id = a.project_id",
+        )?;
+        let mut rows = stmt.query([])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(ActiveSession {
+                project_name: row.get(0)?,
+                phase: row.get(1)?,
+                started_at: row.get(2)?,
+                elapsed_seconds: row.get(3)?,
+                paused: row.get(4)?,
+                pause_count: row.get(5)?,
+                cycle: row.get(6)?,
+                rest_count: row.get(7)?,
+                settings: TimerSettings {
+                    focus_seconds: row.get(8)?,
+                    rest_seconds: row.get(9)?,
+                    long_rest_seconds: row.get(10)?,
+                    long_break_interval: row.get(12)?,
+                    target_cycles: row.get(13)?,
+                    count_skipped_rests: row.get(14)?,
+                    flow: row.get(15)?,
+                },
+                adjustment_seconds: row.get(16)?,
+                updated_at: row.get(11)?,
+            })),
+            None => Ok(None),
+        }
+    }
+    pub fn clear_active_session(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM active_session", [])?;
+        Ok(())
+    }
+    pub fn get_presets(&self) -> Result<Vec<Preset>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{} ORDER BY id", PRESET_SELECT))?;
+        let preset_iter = stmt.query_map([], Preset::from_row)?;
+        preset_iter.collect()
+    }
+    pub fn get_preset(&self, name: &str) -> Result<Option<Preset>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{} WHERE name_key = ?1", PRESET_SELECT))?;
+        let mut rows = stmt.query(params![project_key(name)])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Preset::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+    // a name that is already taken fails with a constraint violation
+    pub fn create_preset(&self, preset: &Preset) -> Result<i64> {
+        let name = normalize_project_name(&preset.name);
+        self.conn.execute(
+            "INSERT INTO presets (name, name_key, focus_seconds, rest_seconds, long_rest_seconds)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                name,
+                project_key(&name),
+                preset.focus_seconds,
+                preset.rest_seconds,
+                preset.long_rest_seconds
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+    // replaces the preset called `name`, which may be renamed along the way
+    pub fn update_preset(&self, name: &str, preset: &Preset) -> Result<bool> {
+        let new_name = normalize_project_name(&preset.name);
         let updated = self.conn.execute(
-            "UPDATE project_with_journal SET time_invested = ?1 WHERE project_name = ?2",
-            params![time_invested, project_name],
+            "UPDATE presets SET name = ?1, name_key = ?2, focus_seconds = ?3, rest_seconds = ?4,
+                long_rest_seconds = ?5
+             WHERE name_key = ?6",
+            params![
+                new_name,
+                project_key(&new_name),
+                preset.focus_seconds,
+                preset.rest_seconds,
+                preset.long_rest_seconds,
+                project_key(name)
+            ],
         )?;
         Ok(updated > 0)
     }
-    pub fn delete_project(&self, project_name: &str) -> Result<bool> {
+    pub fn delete_preset(&self, name: &str) -> Result<bool> {
+        let deleted = self.conn.execute(
+            "DELETE FROM presets WHERE name_key = ?1",
+            params![project_key(name)],
+        )?;
+        Ok(deleted > 0)
+    }
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT value FROM settings WHERE key = ?1")?;
+        let mut rows = stmt.query(params![key])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+    pub fn unset_setting(&self, key: &str) -> Result<bool> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM settings WHERE key = ?1", params![key])?;
+        Ok(deleted > 0)
+    }
+    pub fn delete_session(&self, session_id: i64) -> Result<bool> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM sessions WHERE id = ?1", params![session_id])?;
+        Ok(deleted > 0)
+    }
+    // any recorded session, of any project or phase, sharing time with [started_at, ended_at)
+    pub fn find_overlapping_session(
+        &self,
+        started_at: i64,
+        ended_at: i64,
+        excluding: Option<i64>,
+    ) -> Result<Option<SessionRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{} WHERE s.started_at < ?2 AND s.ended_at > ?1 AND s.id IS NOT ?3
+             ORDER BY s.started_at LIMIT 1",
+            SESSION_SELECT
+        ))?;
+        let mut rows = stmt.query(params![started_at, ended_at, excluding])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(SessionRecord::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+    // "YYYY-MM-DD HH:MM[:SS]" or "HH:MM" (today) in local time, to unix seconds
+    pub fn parse_local_time(&self, text: &str) -> Result<Option<i64>> {
+        let text = text.trim().replace('T', " ");
+        self.conn.query_row(
+            "SELECT CAST(strftime('%s', CASE WHEN instr(?1, '-') > 0 THEN ?1
+                 ELSE date('now', 'localtime') || ' ' || ?1 END, 'utc') AS INTEGER)",
+            params![text],
+            |row| row.get(0),
+        )
+    }
+    pub fn format_local_time(&self, timestamp: i64) -> Result<String> {
+        self.conn.query_row(
+            "SELECT strftime('%Y-%m-%d %H:%M', ?1, 'unixepoch', 'localtime')",
+            params![timestamp],
+            |row| row.get(0),
+        )
+    }
+    // moves the journal along with the row; a name taken by another
+    // project fails with a constraint violation and nothing is changed
+    pub fn rename_project(&self, project_name: &str, new_name: &str) -> Result<bool> {
+        let Some(project) = self.get_single_project(project_name)? else {
+            return Ok(false);
+        };
+        let new_name = normalize_project_name(new_name);
+        let old_journal = project.path_getter();
+        let journal_name = journal_file_name(&new_name);
+        let new_journal = self.journal_dir.join(&journal_name);
+        let case_only = project_key(project.name_getter()) == project_key(&new_name);
+
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE project_with_journal SET project_name = ?1, name_key = ?2, journal_path = ?3 WHERE name_key = ?4",
+            params![
+                new_name,
+                project_key(&new_name),
+                journal_name,
+                project_key(project_name)
+            ],
+        )?;
+        let move_journal = *old_journal != new_journal && old_journal.exists();
+        if move_journal {
+            if new_journal.exists() && !case_only {
+                return Err(db_error(format!(
+                    "A journal file already exists at '{}'.",
+                    new_journal.display()
+                )));
+            }
+            std::fs::rename(old_journal, &new_journal)
+                .map_err(|e| db_error(format!("Failed to move journal file: {}", e)))?;
+        }
+        // the row keeps the old journal path unless the commit lands
+        if let Err(e) = tx.commit() {
+            if move_journal {
+                let _ = std::fs::rename(&new_journal, old_journal);
+            }
+            return Err(e);
+        }
+        Ok(true)
+    }
+    pub fn get_project_timer_defaults(
+        &self,
+        project_name: &str,
+    ) -> Result<Option<ProjectTimerDefaults>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT focus_seconds, rest_seconds, long_rest_seconds, long_break_interval
+             FROM project_with_journal WHERE name_key = ?1 AND focus_seconds IS NOT NULL",
+        )?;
+        let mut rows = stmt.query(params![project_key(project_name)])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(ProjectTimerDefaults {
+                focus_seconds: row.get(0)?,
+                rest_seconds: row.get(1)?,
+                long_rest_seconds: row.get(2)?,
+                long_break_interval: row.get(3)?,
+            })),
+            None => Ok(None),
+        }
+    }
+    // None clears them, so the project follows the global defaults again
+    pub fn set_project_timer_defaults(
+        &self,
+        project_name: &str,
+        defaults: Option<&ProjectTimerDefaults>,
+    ) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE project_with_journal SET focus_seconds = ?1, rest_seconds = ?2,
+                long_rest_seconds = ?3, long_break_interval = ?4
+             WHERE name_key = ?5",
+            params![
+                defaults.map(|d| d.focus_seconds),
+                defaults.map(|d| d.rest_seconds),
+                defaults.map(|d| d.long_rest_seconds),
+                defaults.map(|d| d.long_break_interval),
+                project_key(project_name)
+            ],
+        )?;
+        Ok(updated > 0)
+    }
+    pub fn archive_project(&self, project_name: &str) -> Result<bool> {
+        let archived = self.conn.execute(
+            "UPDATE project_with_journal SET archived_at = ?1 WHERE name_key = ?2 AND archived_at IS NULL",
+            params![unix_now(), project_key(project_name)],
+        )?;
+        Ok(archived > 0)
+    }
+    pub fn restore_project(&self, project_name: &str) -> Result<bool> {
+        let restored = self.conn.execute(
+            "UPDATE project_with_journal SET archived_at = NULL WHERE name_key = ?1 AND archived_at IS NOT NULL",
+            params![project_key(project_name)],
+        )?;
+        Ok(restored > 0)
+    }
+    // permanent: only archived projects can be purged
+    pub fn purge_project(&self, project_name: &str) -> Result<bool> {
         //delete the journal file associated with the project
-        if let Some(project) = self.get_single_project(project_name)? {
-            let journal_path = project.path_getter();
-            if journal_path.exists() {
-                std::fs::remove_file(journal_path).map_err(|e| {
-                    rusqlite::Error::FromSqlConversionFailure(
-                        0,
-                        rusqlite::types::Type::Text,
-                        Box::new(e),
-                    )
-                })?;
+        match self.get_single_project(project_name)? {
+            Some(project) if project.archived_getter() => {
+                let journal_path = project.path_getter();
+                if journal_path.exists() {
+                    std::fs::remove_file(journal_path).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            0,
+                            rusqlite::types::Type::Text,
+                            Box::new(e),
+                        )
+                    })?;
+                }
             }
+            _ => return Ok(false),
         }
-        //delete the project from the database
+        //delete the project and its sessions from the database
         let deleted = self.conn.execute(
-            "DELETE FROM project_with_journal WHERE project_name = ?1",
-            params![project_name],
+            "DELETE FROM project_with_journal WHERE name_key = ?1 AND archived_at IS NOT NULL",
+            params![project_key(project_name)],
         )?;
         Ok(deleted > 0)
     }